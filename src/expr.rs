@@ -1,6 +1,10 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::fmt;
+
 use crate::token::Token;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Binary {
         left: Box<Expr>,
@@ -19,8 +23,40 @@ pub enum Expr {
     },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Object {
-    Number(f32),
+    Number(f64),
     String(String),
+    Bool(bool),
+    Nil,
+}
+
+/// Prints the expression in parenthesized prefix form, e.g. `(* (+ 1 2) 3)`,
+/// for debugging the parser.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Binary {
+                left,
+                operator,
+                right,
+            } => write!(f, "({} {} {})", operator.token_type(), left, right),
+            Self::Grouping { expression } => write!(f, "(group {})", expression),
+            Self::Literal { value } => write!(f, "{}", value),
+            Self::Unary { operator, right } => write!(f, "({} {})", operator.token_type(), right),
+        }
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // integral numbers print without a trailing `.0`
+            Self::Number(number) => write!(f, "{}", number),
+            // quoted, so that a string never looks like another literal
+            Self::String(string) => write!(f, "{:?}", string),
+            Self::Bool(bool) => write!(f, "{}", bool),
+            Self::Nil => write!(f, "nil"),
+        }
+    }
 }
@@ -0,0 +1,453 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use crate::expr::{Expr, Object};
+use crate::token::{Token, TokenType};
+
+/// A tree-walking interpreter that evaluates expressions.
+#[derive(Debug, Default)]
+pub struct Interpreter {
+    /// Arithmetic and comparisons with a `nil` operand yield `nil`.
+    nil_propagation: bool,
+}
+
+/// A runtime value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Str(String),
+    Bool(bool),
+    Nil,
+}
+
+#[derive(Debug)]
+pub enum RuntimeErrorType {
+    OperandMustBeNumber,
+    OperandsMustBeNumbers,
+    OperandsMustBeNumbersOrStrings,
+    DivisionByZero,
+    /// Only raised without std, which has no general `powf`.
+    FractionalExponent,
+}
+
+impl fmt::Display for RuntimeErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OperandMustBeNumber => write!(f, "operand must be a number"),
+            Self::OperandsMustBeNumbers => write!(f, "operands must be numbers"),
+            Self::OperandsMustBeNumbersOrStrings => {
+                write!(f, "operands must be two numbers or two strings")
+            }
+            Self::DivisionByZero => write!(f, "division by zero"),
+            Self::FractionalExponent => {
+                write!(f, "fractional exponents need the std feature")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    error: RuntimeErrorType,
+    line: usize,
+    column: usize,
+}
+
+impl RuntimeError {
+    /// Builds an error reported at the operator `token`.
+    fn new(error: RuntimeErrorType, token: &Token) -> Self {
+        Self {
+            error,
+            line: token.line(),
+            column: token.column(),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}:{}] Error: {}",
+            self.line, self.column, self.error
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RuntimeErrorType {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RuntimeError {}
+
+impl Value {
+    /// Only `nil` and `false` are falsey.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Self::Nil | Self::Bool(false))
+    }
+}
+
+impl From<&Object> for Value {
+    fn from(object: &Object) -> Self {
+        match object {
+            Object::Number(number) => Self::Number(*number),
+            Object::String(string) => Self::Str(string.clone()),
+            Object::Bool(bool) => Self::Bool(*bool),
+            Object::Nil => Self::Nil,
+        }
+    }
+}
+
+/// Prints the value the way Lox's `print` does: strings without quotes and
+/// integral numbers without a trailing `.0`.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{}", number),
+            Self::Str(string) => write!(f, "{}", string),
+            Self::Bool(bool) => write!(f, "{}", bool),
+            Self::Nil => write!(f, "nil"),
+        }
+    }
+}
+
+/// Formats `value` unambiguously, for echoing in the REPL: like `Display`,
+/// but strings are quoted and escaped, so `"1"` and `1` differ.
+pub fn debug_string(value: &Value) -> String {
+    match value {
+        Value::Str(string) => format!("{:?}", string),
+        value => value.to_string(),
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            nil_propagation: false,
+        }
+    }
+
+    /// Makes arithmetic and comparisons with a `nil` operand evaluate to
+    /// `nil` instead of failing, so `nil + 1` and `1 < nil` are both `nil`.
+    /// Equality and `!` are unaffected.
+    pub fn nil_propagation(mut self, enabled: bool) -> Self {
+        self.nil_propagation = enabled;
+        self
+    }
+
+    /// Evaluates a single parsed expression, for embedders and the REPL.
+    pub fn interpret_expr(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.evaluate(expr)
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        match expr {
+            Expr::Literal { value } => Ok(value.into()),
+            Expr::Grouping { expression } => self.evaluate(expression),
+            Expr::Unary { operator, right } => {
+                let right = self.evaluate(right)?;
+                self.unary(operator, right)
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                self.binary(left, operator, right)
+            }
+        }
+    }
+
+    fn unary(&mut self, operator: &Token, right: Value) -> Result<Value, RuntimeError> {
+        match operator.token_type() {
+            TokenType::Bang => Ok(Value::Bool(!right.is_truthy())),
+            TokenType::Minus => match right {
+                Value::Number(number) => Ok(Value::Number(-number)),
+                Value::Nil if self.nil_propagation => Ok(Value::Nil),
+                _ => Err(RuntimeError::new(
+                    RuntimeErrorType::OperandMustBeNumber,
+                    operator,
+                )),
+            },
+            _ => unreachable!("the parser only produces `!` and `-` unary operators"),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        left: Value,
+        operator: &Token,
+        right: Value,
+    ) -> Result<Value, RuntimeError> {
+        let type_ = operator.token_type();
+
+        // operators that take any operands
+        match type_ {
+            TokenType::EqualEqual => return Ok(Value::Bool(left == right)),
+            TokenType::BangEqual => return Ok(Value::Bool(left != right)),
+            _ if self.nil_propagation && (left == Value::Nil || right == Value::Nil) => {
+                return Ok(Value::Nil)
+            }
+            TokenType::Plus => {
+                return match (left, right) {
+                    (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+                    (Value::Str(a), Value::Str(b)) => Ok(Value::Str(a + &b)),
+                    _ => Err(RuntimeError::new(
+                        RuntimeErrorType::OperandsMustBeNumbersOrStrings,
+                        operator,
+                    )),
+                };
+            }
+            _ => (),
+        }
+
+        // the rest only take numbers
+        let (a, b) = match (left, right) {
+            (Value::Number(a), Value::Number(b)) => (a, b),
+            _ => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorType::OperandsMustBeNumbers,
+                    operator,
+                ))
+            }
+        };
+
+        let value = match type_ {
+            TokenType::Minus => Value::Number(a - b),
+            TokenType::Star => Value::Number(a * b),
+            TokenType::Slash => {
+                if b == 0.0 {
+                    return Err(RuntimeError::new(
+                        RuntimeErrorType::DivisionByZero,
+                        operator,
+                    ));
+                }
+                Value::Number(a / b)
+            }
+            TokenType::Percent => {
+                if b == 0.0 {
+                    return Err(RuntimeError::new(
+                        RuntimeErrorType::DivisionByZero,
+                        operator,
+                    ));
+                }
+                Value::Number(a % b)
+            }
+            TokenType::StarStar => match pow(a, b) {
+                Some(number) => Value::Number(number),
+                None => {
+                    return Err(RuntimeError::new(
+                        RuntimeErrorType::FractionalExponent,
+                        operator,
+                    ))
+                }
+            },
+            TokenType::Greater => Value::Bool(a > b),
+            TokenType::GreaterEqual => Value::Bool(a >= b),
+            TokenType::Less => Value::Bool(a < b),
+            TokenType::LessEqual => Value::Bool(a <= b),
+            _ => unreachable!("the parser only produces known binary operators"),
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(feature = "std")]
+fn pow(base: f64, exponent: f64) -> Option<f64> {
+    Some(base.powf(exponent))
+}
+
+/// Without std there is no `powf`, so only whole exponents are supported,
+/// by repeated squaring.
+#[cfg(not(feature = "std"))]
+fn pow(base: f64, exponent: f64) -> Option<f64> {
+    // also rules out NaN, infinities and exponents beyond `i64`
+    let whole = exponent as i64;
+    if whole as f64 != exponent {
+        return None;
+    }
+
+    let mut n = whole.unsigned_abs();
+    let mut square = base;
+    let mut result = 1.0;
+    while n > 0 {
+        if n & 1 == 1 {
+            result *= square;
+        }
+        square *= square;
+        n >>= 1;
+    }
+    Some(if whole < 0 { 1.0 / result } else { result })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    /// Evaluates `source` as an expression, with runtime errors as messages.
+    fn eval_with(interpreter: &mut Interpreter, source: &str) -> Result<Value, String> {
+        let tokens = Scanner::new(source.into()).scan_tokens().unwrap();
+        let expr = Parser::new(tokens).parse().unwrap();
+        interpreter
+            .interpret_expr(&expr)
+            .map_err(|error| error.to_string())
+    }
+
+    fn eval(source: &str) -> Result<Value, String> {
+        eval_with(&mut Interpreter::new(), source)
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(eval("2 + 3 * 4"), Ok(Value::Number(14.0)));
+        assert_eq!(eval("(2 + 3) * 4"), Ok(Value::Number(20.0)));
+        assert_eq!(eval("7 - 2 / 4"), Ok(Value::Number(6.5)));
+        assert_eq!(eval("-(1 + 2)"), Ok(Value::Number(-3.0)));
+        assert_eq!(eval("2 ** 10"), Ok(Value::Number(1024.0)));
+        assert_eq!(eval("2 ** -1"), Ok(Value::Number(0.5)));
+        assert_eq!(eval("-2 ** 2"), Ok(Value::Number(-4.0)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn fractional_exponents() {
+        assert_eq!(eval("4 ** 0.5"), Ok(Value::Number(2.0)));
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn fractional_exponents() {
+        assert_eq!(
+            eval("4 ** 0.5"),
+            Err("[line 1:3] Error: fractional exponents need the std feature".into())
+        );
+    }
+
+    #[test]
+    fn string_concatenation() {
+        assert_eq!(eval("\"a\" + \"b\""), Ok(Value::Str("ab".into())));
+    }
+
+    #[test]
+    fn type_errors() {
+        assert_eq!(
+            eval("1 + \"a\""),
+            Err("[line 1:3] Error: operands must be two numbers or two strings".into())
+        );
+        assert_eq!(
+            eval("\"a\" < \"b\""),
+            Err("[line 1:5] Error: operands must be numbers".into())
+        );
+        assert_eq!(
+            eval("-\"a\""),
+            Err("[line 1:1] Error: operand must be a number".into())
+        );
+        assert_eq!(
+            eval("true > 2"),
+            Err("[line 1:6] Error: operands must be numbers".into())
+        );
+        assert_eq!(
+            eval("\"a\" < 2"),
+            Err("[line 1:5] Error: operands must be numbers".into())
+        );
+        assert_eq!(
+            eval("1 < nil"),
+            Err("[line 1:3] Error: operands must be numbers".into())
+        );
+        assert_eq!(
+            eval("2 ** \"a\""),
+            Err("[line 1:3] Error: operands must be numbers".into())
+        );
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(
+            eval("1 / 0"),
+            Err("[line 1:3] Error: division by zero".into())
+        );
+    }
+
+    #[test]
+    fn truthiness_and_equality() {
+        assert_eq!(eval("!nil"), Ok(Value::Bool(true)));
+        assert_eq!(eval("!false"), Ok(Value::Bool(true)));
+        assert_eq!(eval("!0"), Ok(Value::Bool(false)));
+        assert_eq!(eval("!\"\""), Ok(Value::Bool(false)));
+        assert_eq!(eval("1 == 1"), Ok(Value::Bool(true)));
+        assert_eq!(eval("nil == false"), Ok(Value::Bool(false)));
+        assert_eq!(eval("\"1\" != 1"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(eval("1 + 2").unwrap().to_string(), "3");
+        assert_eq!(eval("0.5").unwrap().to_string(), "0.5");
+        assert_eq!(eval("\"a\" + \"b\"").unwrap().to_string(), "ab");
+        assert_eq!(eval("nil").unwrap().to_string(), "nil");
+    }
+
+    #[test]
+    fn debug_strings() {
+        let value = eval("\"a\"").unwrap();
+        assert_eq!(value.to_string(), "a");
+        assert_eq!(debug_string(&value), "\"a\"");
+        assert_eq!(debug_string(&eval("\"\\\"\\n\"").unwrap()), r#""\"\n""#);
+        assert_eq!(debug_string(&eval("1 + 2").unwrap()), "3");
+        assert_eq!(debug_string(&eval("true").unwrap()), "true");
+        assert_eq!(debug_string(&eval("nil").unwrap()), "nil");
+    }
+
+    #[test]
+    fn nil_propagation() {
+        let mut interpreter = Interpreter::new().nil_propagation(true);
+        for source in ["nil + 1", "1 < nil", "nil * nil", "-nil", "\"a\" + nil"] {
+            assert_eq!(
+                eval_with(&mut interpreter, source),
+                Ok(Value::Nil),
+                "{}",
+                source
+            );
+        }
+        // equality and `!` are unaffected
+        assert_eq!(
+            eval_with(&mut interpreter, "nil == nil"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(eval_with(&mut interpreter, "!nil"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn nil_is_strict_by_default() {
+        assert_eq!(
+            eval("nil + 1"),
+            Err("[line 1:5] Error: operands must be two numbers or two strings".into())
+        );
+        assert_eq!(
+            eval("1 < nil"),
+            Err("[line 1:3] Error: operands must be numbers".into())
+        );
+        assert_eq!(
+            eval("-nil"),
+            Err("[line 1:1] Error: operand must be a number".into())
+        );
+    }
+
+    #[test]
+    fn interpret_expr_returns_the_value() {
+        let tokens = Scanner::new("1 + nil".into()).scan_tokens().unwrap();
+        let expr = Parser::new(tokens).parse().unwrap();
+        // the same interpreter serves every call, keeping its configuration
+        let mut interpreter = Interpreter::new().nil_propagation(true);
+        assert_eq!(interpreter.interpret_expr(&expr).ok(), Some(Value::Nil));
+        assert_eq!(interpreter.interpret_expr(&expr).ok(), Some(Value::Nil));
+        assert!(Interpreter::new().interpret_expr(&expr).is_err());
+    }
+}
@@ -1,2 +1,22 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use scanner::{Scanner, ScannerError, ScannerWarning};
+use token::Token;
+
+pub mod expr;
+pub mod interpreter;
+pub mod parser;
 pub mod scanner;
+pub mod span;
 pub mod token;
+
+/// Runs `source` and returns its tokens, or every scanner error, along with
+/// any warnings. Nothing is printed; that is left to the caller.
+pub fn run(source: &str) -> (Result<Vec<Token>, Vec<ScannerError>>, Vec<ScannerWarning>) {
+    Scanner::new(source.to_string()).scan_tokens_with_warnings()
+}
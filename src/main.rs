@@ -1,12 +1,39 @@
 use std::io::{self, Write};
-use std::{env, fs};
+use std::{env, fs, mem, process};
 
-use anyhow::{bail, Result};
+use anyhow::Result;
 
-use rlox::scanner::Scanner;
+use rlox::interpreter::{debug_string, Interpreter};
+use rlox::parser::Parser;
+use rlox::scanner::{Scanner, ScannerErrorType};
+use rlox::token::{Token, TokenType};
+
+/// How running a program ended, mapped to an exit code by `main`.
+#[derive(Debug, PartialEq)]
+enum Status {
+    Success,
+    /// The command line was malformed.
+    UsageError,
+    /// The program failed to scan or parse.
+    StaticError,
+    /// The program failed while running.
+    RuntimeError,
+}
+
+impl Status {
+    /// The exit code used by the reference Lox implementation.
+    fn exit_code(&self) -> i32 {
+        match self {
+            Self::Success => 0,
+            Self::UsageError => 64,
+            Self::StaticError => 65,
+            Self::RuntimeError => 70,
+        }
+    }
+}
 
 fn main() -> Result<()> {
-    // usage: ./rlox [file.lox]
+    // usage: ./rlox [--emit=tokens-json] [file.lox]
     // run script if provided, otherwise run REPL
 
     let mut args = env::args();
@@ -14,49 +41,223 @@ fn main() -> Result<()> {
     // skip the first arg (binary name)
     args.next();
 
-    match args.next() {
-        Some(path) => run_file(path),
-        None => run_repl(),
+    let mut arg = args.next();
+    // print the tokens as JSON instead of debug output
+    let json = arg.as_deref() == Some("--emit=tokens-json");
+    if json {
+        arg = args.next();
     }
+
+    let status = match arg {
+        Some(path) => run_file(path, json)?,
+        None if !json => return run_repl(),
+        None => {
+            eprintln!("usage: rlox [--emit=tokens-json] [file.lox]");
+            Status::UsageError
+        }
+    };
+
+    if status != Status::Success {
+        process::exit(status.exit_code());
+    }
+    Ok(())
 }
 
-fn run_file(path: String) -> Result<()> {
+fn run_file(path: String, json: bool) -> Result<Status> {
     let source = fs::read_to_string(path)?;
-    run(source)
+    Ok(run(&source, json))
 }
 
 fn run_repl() -> Result<()> {
+    // state persists across lines
+    let mut interpreter = Interpreter::new();
+    let mut buffer = String::new();
+
     loop {
-        // print prompt
-        print!("> ");
+        // print prompt, or a continuation prompt for unfinished input
+        print!("{}", if buffer.is_empty() { "> " } else { "... " });
         io::stdout().flush()?;
 
-        let mut buffer = String::new();
         // if read 0 bytes, exit
         if io::stdin().read_line(&mut buffer)? == 0 {
             return Ok(());
         }
 
-        // ignore error
-        let _ = run(buffer);
+        // a blank line is not an expression, just prompt again
+        if buffer.trim().is_empty() {
+            buffer.clear();
+            continue;
+        }
+
+        // keep reading lines until the input is complete
+        if !is_complete(&buffer) {
+            continue;
+        }
+
+        // errors are already reported, keep going
+        eval(&mem::take(&mut buffer), &mut interpreter);
+    }
+}
+
+/// Whether the input read so far could be a complete program: brackets are
+/// balanced and no string or comment is left open.
+fn is_complete(source: &str) -> bool {
+    let mut scanner = Scanner::new(source.to_string());
+    let mut depth = 0;
+
+    for result in scanner.tokens() {
+        match result {
+            Ok(token) => match token.token_type() {
+                TokenType::LeftParen | TokenType::LeftBrace => depth += 1,
+                TokenType::RightParen | TokenType::RightBrace => depth -= 1,
+                _ => (),
+            },
+            Err(error) => {
+                if matches!(
+                    error.error_type(),
+                    ScannerErrorType::UnterminatedString | ScannerErrorType::UnterminatedComment
+                ) {
+                    return false;
+                }
+            }
+        }
     }
+
+    // extra closing brackets are left for the parser to report
+    depth <= 0
 }
 
-fn run(source: String) -> Result<()> {
-    let scanner = Scanner::new(source);
-    let tokens = match scanner.scan_tokens() {
+fn run(source: &str, json: bool) -> Status {
+    let tokens = match scan(source) {
         Ok(tokens) => tokens,
+        Err(status) => return status,
+    };
+
+    if json {
+        println!("{}", tokens_json(&tokens, source));
+    } else {
+        for token in tokens {
+            // tokens only hold spans, so show what they were scanned from
+            println!("{:?} {:?}", token, token.lexeme(source));
+        }
+    }
+
+    Status::Success
+}
+
+/// Formats the tokens as a JSON array, one object per token:
+///
+/// ```text
+/// {"type": "Number", "lexeme": "1", "line": 1, "column": 1, "span": {"start": 0, "end": 1}}
+/// ```
+///
+/// The type is the token's `TokenType::name`.
+fn tokens_json(tokens: &[Token], source: &str) -> String {
+    let objects: Vec<String> = tokens
+        .iter()
+        .map(|token| {
+            let span = token.span();
+            format!(
+                r#"  {{"type": {}, "lexeme": {}, "line": {}, "column": {}, "span": {{"start": {}, "end": {}}}}}"#,
+                json_string(token.token_type().name()),
+                json_string(token.lexeme(source)),
+                token.line(),
+                token.column(),
+                span.start,
+                span.end
+            )
+        })
+        .collect();
+
+    // never empty, there is always an EOF token
+    format!("[\n{}\n]", objects.join(",\n"))
+}
+
+/// Quotes and escapes `text` as a JSON string.
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Evaluates a line of REPL input as an expression and prints its value.
+fn eval(source: &str, interpreter: &mut Interpreter) -> Status {
+    let tokens = match scan(source) {
+        Ok(tokens) => tokens,
+        Err(status) => return status,
+    };
+
+    let expr = match Parser::new(tokens).parse() {
+        Ok(expr) => expr,
+        Err(error) => {
+            eprintln!("{}", error);
+            return Status::StaticError;
+        }
+    };
+
+    match interpreter.interpret_expr(&expr) {
+        Ok(value) => {
+            println!("{}", debug_string(&value));
+            Status::Success
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            Status::RuntimeError
+        }
+    }
+}
+
+/// Scans the source through `rlox::run`, printing any warnings and errors.
+fn scan(source: &str) -> Result<Vec<Token>, Status> {
+    let (result, warnings) = rlox::run(source);
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+
+    match result {
+        Ok(tokens) => Ok(tokens),
         Err(errors) => {
             for error in errors {
-                eprintln!("{}", error);
+                eprintln!("{}", error.render(source));
             }
-            bail!("scanner error")
+            Err(Status::StaticError)
         }
-    };
+    }
+}
 
-    for token in tokens {
-        println!("{:?}", token);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(run("1 + 2", false).exit_code(), 0);
+        assert_eq!(Status::UsageError.exit_code(), 64);
+        // a scanner failure is static, an evaluation failure is at runtime
+        assert_eq!(run("@", false).exit_code(), 65);
+        assert_eq!(eval("(1", &mut Interpreter::new()).exit_code(), 65);
+        assert_eq!(eval("1 + nil", &mut Interpreter::new()).exit_code(), 70);
     }
 
-    Ok(())
+    #[test]
+    fn incomplete_input() {
+        assert!(!is_complete("(1 +"));
+        assert!(!is_complete("\"ab"));
+        assert!(!is_complete("1 /* a"));
+        assert!(is_complete("(1)\n"));
+        // left for the parser to report
+        assert!(is_complete("1)\n"));
+    }
 }
@@ -1,12 +1,338 @@
-use crate::token::Token;
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
 
+use crate::expr::{Expr, Object};
+use crate::token::{Token, TokenType};
+
+/// A recursive descent parser that turns a list of tokens into an AST.
 pub struct Parser {
+    /// The tokens to parse, ending with an EOF token.
     tokens: Vec<Token>,
+    /// The next token to be consumed.
     current: usize,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum ParseErrorType {
+    ExpectedExpression,
+    Expected(&'static str),
+}
+
+impl fmt::Display for ParseErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ExpectedExpression => write!(f, "expected expression"),
+            Self::Expected(what) => write!(f, "expected {}", what),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    error: ParseErrorType,
+    line: usize,
+    column: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}:{}] Error: {}",
+            self.line, self.column, self.error
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseErrorType {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
         Self { tokens, current: 0 }
     }
+
+    /// Entry point for parsing. The tokens must form a single expression.
+    pub fn parse(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.expression()?;
+
+        if !self.is_at_end() {
+            return Err(self.error(ParseErrorType::Expected("end of input")));
+        }
+        Ok(expr)
+    }
+
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        self.equality()
+    }
+
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.comparison()?;
+
+        while self.match_(&[TokenType::BangEqual, TokenType::EqualEqual]) {
+            let operator = self.previous();
+            let right = self.comparison()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.term()?;
+
+        while self.match_(&[
+            TokenType::Greater,
+            TokenType::GreaterEqual,
+            TokenType::Less,
+            TokenType::LessEqual,
+        ]) {
+            let operator = self.previous();
+            let right = self.term()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.factor()?;
+
+        while self.match_(&[TokenType::Minus, TokenType::Plus]) {
+            let operator = self.previous();
+            let right = self.factor()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
+
+        while self.match_(&[TokenType::Slash, TokenType::Star, TokenType::Percent]) {
+            let operator = self.previous();
+            let right = self.unary()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// `**` binds tighter than unary operators on its left but not on its
+    /// right, as in Python: `-2 ** 2` is `-(2 ** 2)` and `2 ** -1` is
+    /// `2 ** (-1)`. It is right-associative, so `2 ** 3 ** 2` is
+    /// `2 ** (3 ** 2)`.
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.primary()?;
+
+        if self.match_(&[TokenType::StarStar]) {
+            let operator = self.previous();
+            // recursing through unary also makes `**` right-associative
+            let right = self.unary()?;
+            return Ok(Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.match_(&[TokenType::Bang, TokenType::Minus]) {
+            let operator = self.previous();
+            let right = self.unary()?;
+            return Ok(Expr::Unary {
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        self.power()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let value = match self.peek().token_type() {
+            TokenType::False => Object::Bool(false),
+            TokenType::True => Object::Bool(true),
+            TokenType::Nil => Object::Nil,
+            TokenType::Number(number) => Object::Number(*number),
+            TokenType::String(string) => Object::String(string.clone()),
+            // there is no character type, a character is a one-char string
+            TokenType::Char(c) => Object::String(c.to_string()),
+
+            TokenType::LeftParen => {
+                self.advance();
+                let expression = self.expression()?;
+                self.consume(TokenType::RightParen, "')' after expression")?;
+                return Ok(Expr::Grouping {
+                    expression: Box::new(expression),
+                });
+            }
+
+            _ => return Err(self.error(ParseErrorType::ExpectedExpression)),
+        };
+
+        self.advance();
+        Ok(Expr::Literal { value })
+    }
+
+    /// Consumes the current token if it is any of the given types.
+    fn match_(&mut self, types: &[TokenType]) -> bool {
+        if types.iter().any(|type_| self.check(type_)) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes the current token if it is of the given type, otherwise
+    /// reports that `what` was expected.
+    fn consume(&mut self, type_: TokenType, what: &'static str) -> Result<Token, ParseError> {
+        if self.check(&type_) {
+            self.advance();
+            Ok(self.previous())
+        } else {
+            Err(self.error(ParseErrorType::Expected(what)))
+        }
+    }
+
+    fn check(&self, type_: &TokenType) -> bool {
+        !self.is_at_end() && self.peek().token_type() == type_
+    }
+
+    fn advance(&mut self) {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+    }
+
+    fn is_at_end(&self) -> bool {
+        *self.peek().token_type() == TokenType::Eof
+    }
+
+    /// Returns the current token without consuming it.
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    /// Returns the most recently consumed token.
+    fn previous(&self) -> Token {
+        self.tokens[self.current - 1].clone()
+    }
+
+    /// Builds an error at the current token.
+    fn error(&self, error: ParseErrorType) -> ParseError {
+        let token = self.peek();
+        ParseError {
+            error,
+            line: token.line(),
+            column: token.column(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use super::*;
+    use crate::scanner::Scanner;
+
+    fn parse(source: &str) -> Result<Expr, ParseError> {
+        let tokens = Scanner::new(source.into()).scan_tokens().unwrap();
+        Parser::new(tokens).parse()
+    }
+
+    /// The tree of `source` in prefix form.
+    fn tree(source: &str) -> String {
+        parse(source).unwrap().to_string()
+    }
+
+    fn error(source: &str) -> String {
+        parse(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn factor_binds_tighter_than_term() {
+        let expr = parse("1 + 2 * 3").unwrap();
+        let Expr::Binary {
+            left,
+            operator,
+            right,
+        } = expr
+        else {
+            panic!("expected a binary expression, got {:?}", expr);
+        };
+        assert_eq!(operator.token_type(), &TokenType::Plus);
+        assert_eq!(
+            *left,
+            Expr::Literal {
+                value: Object::Number(1.0)
+            }
+        );
+        assert!(matches!(
+            *right,
+            Expr::Binary { ref operator, .. } if operator.token_type() == &TokenType::Star
+        ));
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(tree("1 - 2 - 3"), "(- (- 1 2) 3)");
+        assert_eq!(tree("1 < 2 == 3 >= 4"), "(== (< 1 2) (>= 3 4))");
+        assert_eq!(tree("!!true"), "(! (! true))");
+        assert_eq!(tree("(1 + 2) * 3"), "(* (group (+ 1 2)) 3)");
+        assert_eq!(tree("6 / 3 % 2"), "(% (/ 6 3) 2)");
+    }
+
+    #[test]
+    fn power_precedence() {
+        assert_eq!(tree("-2 ** 2"), "(- (** 2 2))");
+        assert_eq!(tree("2 ** -1"), "(** 2 (- 1))");
+        assert_eq!(tree("2 ** 3 ** 2"), "(** 2 (** 3 2))");
+        assert_eq!(tree("2 * 3 ** 2"), "(* 2 (** 3 2))");
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(tree("nil"), "nil");
+        assert_eq!(tree("\"a\""), "\"a\"");
+        assert_eq!(tree("'c'"), "\"c\"");
+        assert_eq!(tree("1.5"), "1.5");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error("(1 + 2"),
+            "[line 1:7] Error: expected ')' after expression"
+        );
+        assert_eq!(error("1 +"), "[line 1:4] Error: expected expression");
+        assert_eq!(error("1 2"), "[line 1:3] Error: expected end of input");
+    }
 }
@@ -1,67 +1,524 @@
-use thiserror::Error;
+use alloc::borrow::Cow;
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem::{self, Discriminant};
 
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+use crate::span::Span;
 use crate::token::{Token, TokenType};
 
-/// A scanner that reads source code and produces a list of tokens or errors.
-/// NOTE: Only ASCII characters are supported.
+/// The largest supported source, in bytes, so that offsets fit in a `u32`
+/// on every target. On 32-bit targets a `String` can hold at most
+/// `isize::MAX` bytes, which is already less, so the limit only comes into
+/// play on 64-bit targets.
+pub const MAX_SOURCE_LEN: usize = u32::MAX as usize;
+
+/// A scanner that reads source code and produces a list of tokens or errors,
+/// either all at once or one at a time through `tokens`.
+/// Identifiers follow Unicode's XID rules, as in Rust, so they may contain
+/// letters and combining marks from any script, and string and character
+/// literals any Unicode text. Sources longer than `MAX_SOURCE_LEN` bytes are rejected.
 pub struct Scanner {
     /// The source code to scan.
     source: String,
-    /// The output list of tokens.
-    tokens: Vec<Token>,
-    /// The list of scanner errors.
-    errors: Vec<ScannerError>,
+    /// The largest source accepted, in bytes.
+    max_len: usize,
+    /// Whether the source is all ASCII, so characters are single bytes and
+    /// need no decoding.
+    ascii: bool,
+    /// Tokens and errors scanned but not yet handed out, in source order.
+    pending: VecDeque<Result<Token, ScannerError>>,
+    /// Whether the EOF token has been handed out.
+    done: bool,
+    /// Whether the last token was a line break, or there is none yet.
+    after_newline: bool,
+    /// Whether whitespace or a comment was skipped since the last token.
+    after_whitespace: bool,
+    /// The list of non-fatal diagnostics.
+    warnings: Vec<ScannerWarning>,
     /// The start of the current lexeme being scanned.
     start: usize,
     /// The current character being scanned.
     current: usize,
     /// The current line number.
     line: usize,
+    /// The number of the source's first line.
+    first_line: usize,
+    /// The offset of the first character of the current line.
+    line_start: usize,
+    /// A previously computed (offset, column) on the current line, so that
+    /// columns are counted incrementally rather than from the line start.
+    column_cache: (usize, usize),
+    /// The line and column where the current lexeme starts.
+    start_line: usize,
+    start_column: usize,
+    /// Statistics gathered while scanning.
+    stats: ScanStats,
+    /// Opt-in scanning behaviour.
+    options: Options,
+    /// The leading whitespace seen so far on the current line, or `None`
+    /// once the line has reached its first non-whitespace character.
+    indentation: Option<Indentation>,
+}
+
+/// The kinds of whitespace seen in a line's indentation.
+#[derive(Debug, Default, Clone, Copy)]
+struct Indentation {
+    tabs: bool,
+    spaces: bool,
+}
+
+/// Opt-in scanning behaviour, all disabled by default.
+#[derive(Debug, Default, Clone)]
+struct Options {
+    /// Identifiers may end with a single `?` or `!`.
+    predicate_identifiers: bool,
+    /// A number immediately followed by an identifier character is an error.
+    strict_numbers: bool,
+    /// Warn about lines indented with both tabs and spaces.
+    lint_indentation: bool,
+    /// Emit a `Newline` token at the end of each non-blank line.
+    newline_tokens: bool,
+    /// `'...'` is a string literal rather than a character literal.
+    single_quote_strings: bool,
+    /// Starts a line comment in place of `//`.
+    line_comment_marker: Option<String>,
+}
+
+/// Basic statistics about a scanned source.
+#[derive(Debug, Default)]
+pub struct ScanStats {
+    /// The total number of tokens, including the trailing EOF token.
+    pub tokens: usize,
+    /// The number of lines in the source.
+    pub lines: usize,
+    /// The number of comments skipped.
+    pub comments: usize,
+    /// The number of tokens of each kind, in order of first appearance.
+    kinds: Vec<(Discriminant<TokenType>, usize)>,
+}
+
+impl ScanStats {
+    /// Returns the number of tokens of the same kind as `kind`. Payloads are
+    /// ignored, so `&TokenType::Number(0.0)` counts all number literals.
+    pub fn count(&self, kind: &TokenType) -> usize {
+        let kind = mem::discriminant(kind);
+        self.kinds
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map_or(0, |&(_, count)| count)
+    }
+
+    fn record(&mut self, kind: &TokenType) {
+        self.tokens += 1;
+
+        let kind = mem::discriminant(kind);
+        match self.kinds.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, count)) => *count += 1,
+            None => self.kinds.push((kind, 1)),
+        }
+    }
 }
 
-#[derive(Error, Debug)]
+#[derive(Debug)]
 pub enum ScannerErrorType {
-    #[error("unexpected character '{}'", *.0 as char)]
-    UnexpectedCharacter(u8),
-    #[error("unterminated string")]
+    UnexpectedCharacter(char),
     UnterminatedString,
+    UnterminatedComment,
+    UnterminatedCharLiteral,
+    EmptyCharLiteral,
+    MultiCharLiteral,
+    InvalidEscape(char),
+    InvalidUnicodeEscape,
+    InvalidNumber(String),
+    /// The source is longer than the given number of bytes.
+    SourceTooLarge(usize),
+}
+
+impl fmt::Display for ScannerErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedCharacter(c) => {
+                write!(f, "unexpected character '{}'", c)
+            }
+            Self::UnterminatedString => write!(f, "unterminated string"),
+            Self::UnterminatedComment => write!(f, "unterminated block comment"),
+            Self::UnterminatedCharLiteral => {
+                write!(f, "unterminated character literal")
+            }
+            Self::EmptyCharLiteral => write!(f, "empty character literal"),
+            Self::MultiCharLiteral => {
+                write!(f, "character literal contains more than one character")
+            }
+            Self::InvalidEscape(c) => {
+                write!(f, "invalid escape sequence '\\{}'", c)
+            }
+            Self::InvalidUnicodeEscape => write!(f, "invalid unicode escape"),
+            Self::InvalidNumber(text) => write!(f, "invalid number '{}'", text),
+            Self::SourceTooLarge(max_len) => {
+                write!(f, "source is longer than {} bytes", max_len)
+            }
+        }
+    }
 }
 
-#[derive(Error, Debug)]
-#[error("[line {line}] Error: {error}")]
+#[derive(Debug)]
 pub struct ScannerError {
     error: ScannerErrorType,
     line: usize,
+    column: usize,
+    /// The number of the source's first line, which is not 1 after a
+    /// `rescan` that continues line numbers.
+    first_line: usize,
+}
+
+impl fmt::Display for ScannerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}:{}] Error: {}",
+            self.line, self.column, self.error
+        )
+    }
+}
+
+impl ScannerError {
+    pub fn error_type(&self) -> &ScannerErrorType {
+        &self.error
+    }
+
+    /// Renders the error followed by the offending source line and a caret
+    /// under the reported column:
+    ///
+    /// ```text
+    /// [line 2:5] Error: unexpected character '@'
+    ///   |
+    /// 2 | 1 + @
+    ///   |     ^
+    /// ```
+    ///
+    /// `source` must be the source that was scanned. After a `rescan` that
+    /// continues line numbers, that is the new source alone; the gutter still
+    /// shows the continued line number.
+    pub fn render(&self, source: &str) -> String {
+        let text = source
+            .lines()
+            .nth(self.line - self.first_line)
+            .unwrap_or("");
+        let gutter = self.line.to_string();
+        let pad = " ".repeat(gutter.len());
+
+        // keep tabs so the caret lines up however they are displayed
+        let indent: String = text
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!(
+            "{}\n{} |\n{} | {}\n{} | {}^",
+            self, pad, gutter, text, pad, indent
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ScannerErrorType {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ScannerError {}
+
+#[derive(Debug)]
+pub enum ScannerWarningType {
+    MixedIndentation,
+    ArrowOperator,
+}
+
+impl fmt::Display for ScannerWarningType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MixedIndentation => write!(f, "indentation mixes tabs and spaces"),
+            Self::ArrowOperator => {
+                write!(f, "'=>' is not an operator, did you mean '>=' or '=='?")
+            }
+        }
+    }
+}
+
+/// A non-fatal diagnostic; scanning still succeeds.
+#[derive(Debug)]
+pub struct ScannerWarning {
+    warning: ScannerWarningType,
+    line: usize,
+    column: usize,
+}
+
+impl fmt::Display for ScannerWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}:{}] Warning: {}",
+            self.line, self.column, self.warning
+        )
+    }
 }
 
 impl Scanner {
     pub fn new(source: String) -> Self {
-        Self {
-            source,
-            tokens: Vec::new(),
-            errors: Vec::new(),
+        Self::with_max_len(source, MAX_SOURCE_LEN)
+    }
+
+    /// Like `new`, but rejects sources longer than `max_len` bytes, so the
+    /// limit can be exercised without a gigantic source.
+    pub(crate) fn with_max_len(source: String, max_len: usize) -> Self {
+        let mut scanner = Self {
+            source: String::new(),
+            max_len,
+            ascii: true,
+            pending: VecDeque::new(),
+            done: false,
+            after_newline: true,
+            after_whitespace: false,
+            warnings: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            first_line: 1,
+            line_start: 0,
+            column_cache: (0, 1),
+            start_line: 1,
+            start_column: 1,
+            stats: ScanStats::default(),
+            options: Options::default(),
+            indentation: Some(Indentation::default()),
+        };
+        scanner.rescan(source, false);
+        scanner
+    }
+
+    /// Starts over on a new `source`, keeping this scanner's options and
+    /// buffers, for example to scan successive REPL input with `tokens`.
+    /// With `continue_lines`, line numbers carry on from the line the
+    /// previous source ended on instead of restarting at 1.
+    pub fn rescan(&mut self, source: String, continue_lines: bool) {
+        let line = if continue_lines { self.line } else { 1 };
+
+        self.ascii = source.is_ascii();
+        self.source = source;
+        self.pending.clear();
+        self.done = false;
+        self.after_newline = true;
+        self.after_whitespace = false;
+        self.warnings.clear();
+        self.start = 0;
+        self.current = 0;
+        self.line = line;
+        self.first_line = line;
+        self.line_start = 0;
+        self.column_cache = (0, 1);
+        self.start_line = line;
+        self.start_column = 1;
+        self.stats = ScanStats::default();
+        self.indentation = Some(Indentation::default());
+
+        if self.source.len() > self.max_len {
+            // report it once and skip straight to the EOF token
+            let end = self.source.len();
+            self.add_error_at(ScannerErrorType::SourceTooLarge(self.max_len), line, 1);
+            self.current = end;
+            self.line_start = end;
+            self.column_cache = (end, 1);
         }
     }
 
+    /// Allows identifiers to end with a single `?` or `!`, as in `empty?` or
+    /// `sort!`. A `!` followed by `=` is still scanned as `!=`.
+    pub fn predicate_identifiers(mut self, enabled: bool) -> Self {
+        self.options.predicate_identifiers = enabled;
+        self
+    }
+
+    /// Reports a number immediately followed by letters, like `123abc`, as an
+    /// invalid number instead of scanning a number and an identifier.
+    pub fn strict_numbers(mut self, enabled: bool) -> Self {
+        self.options.strict_numbers = enabled;
+        self
+    }
+
+    /// Warns about lines whose indentation mixes tabs and spaces.
+    pub fn lint_indentation(mut self, enabled: bool) -> Self {
+        self.options.lint_indentation = enabled;
+        self
+    }
+
+    /// Emits a `Newline` token at each line break instead of treating it as
+    /// whitespace. Blank lines, including leading ones, collapse into the
+    /// previous newline.
+    pub fn newline_tokens(mut self, enabled: bool) -> Self {
+        self.options.newline_tokens = enabled;
+        self
+    }
+
+    /// Scans `'...'` as a string literal, with the same escapes as `"..."`,
+    /// instead of as a character literal. A literal must close with the
+    /// quote it opened with.
+    pub fn single_quote_strings(mut self, enabled: bool) -> Self {
+        self.options.single_quote_strings = enabled;
+        self
+    }
+
+    /// Starts line comments with `marker`, such as `--` or `;`, instead of
+    /// `//`. The marker takes precedence over any token it overlaps, so with
+    /// `--` the source `--x` is a comment rather than two minuses, and `//`
+    /// scans as two slashes. Block comments are unaffected.
+    ///
+    /// # Panics
+    ///
+    /// If `marker` is empty or contains a line break.
+    pub fn line_comment_marker(mut self, marker: &str) -> Self {
+        assert!(
+            !marker.is_empty() && !marker.contains('\n'),
+            "a line comment marker must be non-empty and on one line"
+        );
+        self.options.line_comment_marker = Some(marker.into());
+        self
+    }
+
     /// Entry point for scanning.
     pub fn scan_tokens(mut self) -> Result<Vec<Token>, Vec<ScannerError>> {
-        while !self.is_at_end() {
+        self.scan()
+    }
+
+    /// Like `scan_tokens`, but also returns statistics about the source.
+    pub fn scan_tokens_with_stats(mut self) -> Result<(Vec<Token>, ScanStats), Vec<ScannerError>> {
+        let tokens = self.scan()?;
+        self.stats.lines = self.source.lines().count();
+        Ok((tokens, self.stats))
+    }
+
+    /// Like `scan_tokens`, but also hands back the source, which tokens need
+    /// to look up their lexemes.
+    pub fn into_tokens_and_source(mut self) -> (Result<Vec<Token>, Vec<ScannerError>>, String) {
+        let result = self.scan();
+        (result, self.source)
+    }
+
+    /// Like `scan_tokens`, but also returns the non-fatal diagnostics, which
+    /// are reported whether or not scanning succeeds.
+    pub fn scan_tokens_with_warnings(
+        mut self,
+    ) -> (Result<Vec<Token>, Vec<ScannerError>>, Vec<ScannerWarning>) {
+        let result = self.scan();
+        (result, self.warnings)
+    }
+
+    /// Scans the source and groups the tokens by the line they start on, for
+    /// repainting one line at a time. Index `i` holds the tokens of the
+    /// source's `i + 1`th line, and lines without tokens are empty; after a
+    /// `rescan` that continues line numbers, the tokens themselves keep the
+    /// continued numbers. A token that spans several lines, such as a
+    /// multi-line string, belongs to its first line; its `span` gives its
+    /// full extent. The EOF token is left out.
+    pub fn tokens_by_line(self) -> Result<Vec<Vec<Token>>, Vec<ScannerError>> {
+        let first_line = self.first_line;
+        let line_count = self.source.lines().count();
+        let mut lines: Vec<Vec<Token>> = Vec::new();
+        lines.resize_with(line_count, Vec::new);
+
+        for token in self.scan_tokens()? {
+            if *token.token_type() == TokenType::Eof {
+                continue;
+            }
+            lines[token.line() - first_line].push(token);
+        }
+        Ok(lines)
+    }
+
+    /// Like `scan_tokens`, but hands each error to `report` as soon as it is
+    /// found, in source order, instead of collecting them. Returns the tokens
+    /// if there were no errors.
+    pub fn scan_tokens_reporting(
+        mut self,
+        mut report: impl FnMut(ScannerError),
+    ) -> Option<Vec<Token>> {
+        let mut failed = false;
+        let tokens = self.scan_with(|error| {
+            failed = true;
+            report(error);
+        });
+        (!failed).then_some(tokens)
+    }
+
+    /// Scans lazily, yielding each token or error as soon as it is scanned.
+    /// The last item is always the EOF token.
+    pub fn tokens(&mut self) -> impl Iterator<Item = Result<Token, ScannerError>> + '_ {
+        core::iter::from_fn(move || self.next_token())
+    }
+
+    /// Collects every token, or every error if there were any.
+    fn scan(&mut self) -> Result<Vec<Token>, Vec<ScannerError>> {
+        let mut errors = Vec::new();
+        let tokens = self.scan_with(|error| errors.push(error));
+
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Collects every token, handing each error to `report` in source order.
+    fn scan_with(&mut self, mut report: impl FnMut(ScannerError)) -> Vec<Token> {
+        // rough guess of one token per four bytes of source, to avoid
+        // repeatedly growing the token list on large inputs
+        let mut tokens = Vec::with_capacity(self.source.len() / 4);
+
+        for result in self.tokens() {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(error) => report(error),
+            }
+        }
+        tokens
+    }
+
+    fn next_token(&mut self) -> Option<Result<Token, ScannerError>> {
+        loop {
+            if let Some(result) = self.pending.pop_front() {
+                return Some(result);
+            }
+
+            if self.is_at_end() {
+                return self.eof_token().map(Ok);
+            }
+
             // current parse point is the start of the next lexeme
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column_of(self.start);
             self.scan_token();
         }
+    }
 
-        if self.errors.is_empty() {
-            // add EOF token
-            self.tokens
-                .push(Token::new(TokenType::Eof, String::new(), self.line));
-            Ok(self.tokens)
-        } else {
-            Err(self.errors)
+    /// Returns the EOF token the first time it is called, then `None`.
+    fn eof_token(&mut self) -> Option<Token> {
+        if self.done {
+            return None;
         }
+        self.done = true;
+
+        let end = self.source.len();
+        self.stats.record(&TokenType::Eof);
+        Some(Token::new(
+            TokenType::Eof,
+            Span::new(end, end, self.line),
+            self.column_of(end),
+            self.after_whitespace,
+        ))
     }
 
     fn is_at_end(&self) -> bool {
@@ -70,179 +527,1284 @@ impl Scanner {
 
     fn scan_token(&mut self) {
         let c = self.advance();
+        if self.options.lint_indentation {
+            self.check_indentation(c);
+        }
+
         match c {
-            b'(' => self.add_token(TokenType::LeftParen),
-            b')' => self.add_token(TokenType::RightParen),
-            b'{' => self.add_token(TokenType::LeftBrace),
-            b'}' => self.add_token(TokenType::RightBrace),
-            b',' => self.add_token(TokenType::Comma),
-            b'.' => self.add_token(TokenType::Dot),
-            b'-' => self.add_token(TokenType::Minus),
-            b'+' => self.add_token(TokenType::Plus),
-            b';' => self.add_token(TokenType::Semicolon),
-            b'*' => self.add_token(TokenType::Star),
-
-            b'!' => {
-                if self.match_(b'=') {
+            // a custom comment marker wins over the token it starts with
+            _ if self.at_line_comment_marker() => self.line_comment(),
+
+            '(' => self.add_token(TokenType::LeftParen),
+            ')' => self.add_token(TokenType::RightParen),
+            '{' => self.add_token(TokenType::LeftBrace),
+            '}' => self.add_token(TokenType::RightBrace),
+            ',' => self.add_token(TokenType::Comma),
+            '-' => self.add_token(TokenType::Minus),
+            '+' => self.add_token(TokenType::Plus),
+            ';' => self.add_token(TokenType::Semicolon),
+            '%' => self.add_token(TokenType::Percent),
+            '?' => self.add_token(TokenType::Question),
+            ':' => self.add_token(TokenType::Colon),
+
+            '!' => {
+                if self.match_('=') {
                     self.add_token(TokenType::BangEqual);
                 } else {
                     self.add_token(TokenType::Bang);
                 }
             }
-            b'=' => {
-                if self.match_(b'=') {
+            '=' => {
+                if self.match_('=') {
                     self.add_token(TokenType::EqualEqual);
                 } else {
+                    // there is no `=>` operator, it scans as `=` then `>`
+                    if self.peek() == '>' {
+                        self.add_warning(ScannerWarningType::ArrowOperator);
+                    }
                     self.add_token(TokenType::Equal);
                 }
             }
-            b'<' => {
-                if self.match_(b'=') {
+            '<' => {
+                if self.match_('=') {
                     self.add_token(TokenType::LessEqual);
                 } else {
                     self.add_token(TokenType::Less);
                 }
             }
-            b'>' => {
-                if self.match_(b'=') {
+            '>' => {
+                if self.match_('=') {
                     self.add_token(TokenType::GreaterEqual);
                 } else {
                     self.add_token(TokenType::Greater);
                 }
             }
+            '.' => {
+                if self.match_('.') {
+                    self.add_token(TokenType::DotDot);
+                } else {
+                    self.add_token(TokenType::Dot);
+                }
+            }
+            '*' => {
+                if self.match_('*') {
+                    self.add_token(TokenType::StarStar);
+                } else {
+                    self.add_token(TokenType::Star);
+                }
+            }
 
-            b'/' => {
-                if self.match_(b'/') {
-                    // line of comment
-                    while self.peek() != b'\n' && !self.is_at_end() {
-                        self.advance();
-                    }
+            '/' => {
+                if self.options.line_comment_marker.is_none() && self.match_('/') {
+                    self.line_comment();
+                } else if self.match_('*') {
+                    self.block_comment();
                 } else {
                     self.add_token(TokenType::Slash);
                 }
             }
 
-            b' ' | b'\r' | b'\t' => (),
-            b'\n' => self.line += 1,
+            // space, carriage return, tab, form feed and vertical tab
+            ' ' | '\r' | '\t' | '\x0C' | '\x0B' => self.after_whitespace = true,
+            '\n' => {
+                if self.options.newline_tokens && !self.after_newline {
+                    self.add_token(TokenType::Newline);
+                } else {
+                    self.after_whitespace = true;
+                }
+                self.newline();
+            }
 
-            b'"' => self.string(),
+            '"' => self.string('"'),
+            '\'' if self.options.single_quote_strings => self.string('\''),
+            '\'' => self.char_(),
             c if c.is_ascii_digit() => self.number(),
-            c if c.is_ascii_alphabetic() || c == b'_' => self.identifier(),
+            c if is_xid_start(c) || c == '_' => self.identifier(),
 
             _ => self.add_error(ScannerErrorType::UnexpectedCharacter(c)),
         };
+
+        // scanner invariants, checked in debug builds only
+        debug_assert!(
+            self.start <= self.current,
+            "lexeme start is past the cursor"
+        );
+        debug_assert!(
+            self.current <= self.source.len(),
+            "cursor is past the source"
+        );
+        debug_assert!(self.line >= 1, "line numbers start at 1");
+    }
+
+    /// Whether a custom line comment marker starts at the current lexeme.
+    fn at_line_comment_marker(&self) -> bool {
+        self.options
+            .line_comment_marker
+            .as_deref()
+            .is_some_and(|marker| self.source[self.start..].starts_with(marker))
+    }
+
+    /// Consumes the rest of a line comment, its marker's first character
+    /// already consumed.
+    fn line_comment(&mut self) {
+        self.stats.comments += 1;
+        self.after_whitespace = true;
+        while self.peek() != '\n' && !self.is_at_end() {
+            self.advance();
+        }
+    }
+
+    /// Consumes a `/* ... */` comment, the opening `/*` already consumed.
+    /// Block comments nest.
+    fn block_comment(&mut self) {
+        self.stats.comments += 1;
+        self.after_whitespace = true;
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                // reported where the comment was opened
+                self.add_error(ScannerErrorType::UnterminatedComment);
+                return;
+            }
+
+            match self.advance() {
+                '/' if self.match_('*') => depth += 1,
+                '*' if self.match_('/') => depth -= 1,
+                '\n' => self.newline(),
+                _ => (),
+            }
+        }
+    }
+
+    /// Tracks the leading whitespace of each line, given the first character
+    /// of each lexeme.
+    fn check_indentation(&mut self, c: char) {
+        match (c, &mut self.indentation) {
+            ('\n', _) => self.indentation = Some(Indentation::default()),
+            (_, None) => (),
+            ('\t', Some(indentation)) => indentation.tabs = true,
+            (' ', Some(indentation)) => indentation.spaces = true,
+            (_, Some(indentation)) => {
+                if indentation.tabs && indentation.spaces {
+                    self.add_warning(ScannerWarningType::MixedIndentation);
+                }
+                self.indentation = None;
+            }
+        }
+    }
+
+    /// Moves to the next line, the `\n` already consumed.
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+        self.column_cache = (self.current, 1);
+    }
+
+    /// Returns the 1-based column of an offset on the current line, counted
+    /// in characters rather than bytes. In an ASCII source they are the same.
+    fn column_of(&mut self, offset: usize) -> usize {
+        if self.ascii {
+            return offset - self.line_start + 1;
+        }
+
+        let (from, column) = match self.column_cache {
+            (cached, column) if cached <= offset => (cached, column),
+            _ => (self.line_start, 1),
+        };
+        let column = column + self.source[from..offset].chars().count();
+        self.column_cache = (offset, column);
+        column
     }
 
     /// Consumes the current character and returns it.
-    fn advance(&mut self) -> u8 {
-        self.current += 1;
-        self.source.as_bytes()[self.current - 1]
+    fn advance(&mut self) -> char {
+        let c = self.peek();
+        self.current += c.len_utf8();
+        c
     }
 
     fn add_token(&mut self, type_: TokenType) {
-        let text = &self.source[self.start..self.current];
-        self.tokens
-            .push(Token::new(type_, text.to_string(), self.line))
+        let span = Span::new(self.start, self.current, self.start_line);
+        self.stats.record(&type_);
+        self.after_newline = type_ == TokenType::Newline;
+        let token = Token::new(type_, span, self.start_column, self.after_whitespace);
+        self.after_whitespace = false;
+        self.pending.push_back(Ok(token));
+    }
+
+    /// Reports a warning at the start of the current lexeme.
+    fn add_warning(&mut self, warning: ScannerWarningType) {
+        self.warnings.push(ScannerWarning {
+            warning,
+            line: self.start_line,
+            column: self.start_column,
+        });
     }
 
+    /// Reports an error at the start of the current lexeme.
     fn add_error(&mut self, error: ScannerErrorType) {
-        self.errors.push(ScannerError {
+        self.add_error_at(error, self.start_line, self.start_column);
+    }
+
+    fn add_error_at(&mut self, error: ScannerErrorType, line: usize, column: usize) {
+        self.pending.push_back(Err(ScannerError {
             error,
-            line: self.line,
-        });
+            line,
+            column,
+            first_line: self.first_line,
+        }));
     }
 
     /// Consumes the current character if it matches the expected character.
-    fn match_(&mut self, expected: u8) -> bool {
-        match self.source.as_bytes().get(self.current) {
-            None => false,
-            Some(&c) if c != expected => false,
-            _ => {
-                self.current += 1;
-                true
-            }
+    fn match_(&mut self, expected: char) -> bool {
+        if self.is_at_end() || self.peek() != expected {
+            return false;
         }
+        self.current += expected.len_utf8();
+        true
     }
 
     /// Returns the current character without consuming it.
-    fn peek(&self) -> u8 {
-        match self.source.as_bytes().get(self.current) {
-            None => b'\0',
-            Some(&c) => c,
+    fn peek(&self) -> char {
+        self.peek_n(0)
+    }
+
+    /// Returns the character `n` places after the current one without
+    /// consuming anything, or `\0` past the end of the source. An ASCII
+    /// source is indexed by byte rather than decoded.
+    fn peek_n(&self, n: usize) -> char {
+        if self.ascii {
+            let byte = self.source.as_bytes().get(self.current + n);
+            return byte.map_or('\0', |&byte| byte as char);
         }
+        self.source[self.current..].chars().nth(n).unwrap_or('\0')
     }
 
-    fn string(&mut self) {
-        while self.peek() != b'"' && !self.is_at_end() {
-            if self.peek() == b'\n' {
-                self.line += 1;
+    /// Scans a string literal closed by `quote`.
+    fn string(&mut self, quote: char) {
+        // the lexeme keeps the escapes as written, the value is decoded
+        let mut value = String::new();
+
+        while self.peek() != quote && !self.is_at_end() {
+            match self.advance() {
+                '\\' => {
+                    if let Some(c) = self.escape() {
+                        value.push(c);
+                    }
+                }
+                c => {
+                    if c == '\n' {
+                        self.newline();
+                    }
+                    value.push(c);
+                }
             }
-            self.advance();
         }
 
         if self.is_at_end() {
+            // an escaped quote at the very end lands here too, e.g. `"foo\"`
             self.add_error(ScannerErrorType::UnterminatedString);
+            return;
+        }
+
+        // consume the ending quote
+        self.advance();
+
+        self.add_token(TokenType::String(value));
+    }
+
+    fn char_(&mut self) {
+        let mut value = None;
+        let mut count = 0;
+        let mut valid = true;
+
+        while self.peek() != '\'' && self.peek() != '\n' && !self.is_at_end() {
+            let c = match self.advance() {
+                '\\' => self.escape(),
+                c => Some(c),
+            };
+            match c {
+                Some(c) => value = Some(c),
+                None => valid = false,
+            }
+            count += 1;
+        }
+
+        if self.peek() != '\'' {
+            self.add_error(ScannerErrorType::UnterminatedCharLiteral);
+            return;
         }
 
-        // consume the ending `"`
+        // consume the ending `'`
         self.advance();
 
-        let value = &self.source[self.start + 1..self.current - 1];
-        self.add_token(TokenType::String(value.to_string()));
+        if !valid {
+            // the bad escape has already been reported
+            return;
+        }
+        match (count, value) {
+            (1, Some(c)) => self.add_token(TokenType::Char(c)),
+            (0, _) => self.add_error(ScannerErrorType::EmptyCharLiteral),
+            _ => self.add_error(ScannerErrorType::MultiCharLiteral),
+        }
     }
 
+    /// Consumes an escape sequence, the `\` already consumed, and returns the
+    /// character it stands for. Reports an error and returns `None` if the
+    /// escape is invalid.
+    fn escape(&mut self) -> Option<char> {
+        if self.is_at_end() {
+            // the unterminated literal is reported by the caller
+            return None;
+        }
+
+        // errors point at the backslash
+        let line = self.line;
+        let column = self.column_of(self.current - 1);
+
+        let c = match self.advance() {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' => Ok('\\'),
+            '\'' => Ok('\''),
+            '"' => Ok('"'),
+            'x' => self.hex_escape(),
+            'u' => self.unicode_escape(),
+            c => Err(ScannerErrorType::InvalidEscape(c)),
+        };
+        match c {
+            Ok(c) => Some(c),
+            Err(error) => {
+                self.add_error_at(error, line, column);
+                None
+            }
+        }
+    }
+
+    /// Consumes the `NN` part of a `\xNN` escape, exactly two hex digits naming
+    /// a code point from 0 to 255 (not a raw byte).
+    fn hex_escape(&mut self) -> Result<char, ScannerErrorType> {
+        if !(self.peek().is_ascii_hexdigit() && self.peek_n(1).is_ascii_hexdigit()) {
+            return Err(ScannerErrorType::InvalidEscape('x'));
+        }
+
+        self.advance();
+        self.advance();
+        let digits = &self.source[self.current - 2..self.current];
+        u8::from_str_radix(digits, 16)
+            .map(char::from)
+            .map_err(|_| ScannerErrorType::InvalidEscape('x'))
+    }
+
+    /// Consumes the `{XXXX}` part of a `\u{XXXX}` escape, with one to six hex
+    /// digits naming a unicode scalar value.
+    fn unicode_escape(&mut self) -> Result<char, ScannerErrorType> {
+        if !self.match_('{') {
+            return Err(ScannerErrorType::InvalidUnicodeEscape);
+        }
+
+        let digits_start = self.current;
+        while self.peek().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let digits_end = self.current;
+
+        let c = if (1..=6).contains(&(digits_end - digits_start)) && self.match_('}') {
+            let digits = &self.source[digits_start..digits_end];
+            u32::from_str_radix(digits, 16)
+                .ok()
+                .and_then(char::from_u32)
+        } else {
+            None
+        };
+        c.ok_or(ScannerErrorType::InvalidUnicodeEscape)
+    }
+
+    /// Scans a number literal: one or more ASCII digits, optionally followed
+    /// by `.` and one or more digits, or an integer written in hex (`0xFF`)
+    /// or binary (`0b1010`), with a case-insensitive prefix. Digits may be
+    /// grouped with `_`, as in `1_000_000`, but never at the start or end of
+    /// a run of digits. There are no signs or exponents, so `1,000` is a
+    /// number, a comma and another number. Decimal values are parsed with
+    /// `str::parse`, which does not depend on locale.
     fn number(&mut self) {
-        while self.peek().is_ascii_digit() {
+        let radix = match (self.source.as_bytes()[self.start], self.peek()) {
+            (b'0', 'x' | 'X') => 16,
+            (b'0', 'b' | 'B') => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            // consume the prefix letter
             self.advance();
         }
 
-        // fractional part
-        if self.peek() == b'.' && self.peek_next().is_ascii_digit() {
+        self.digits(radix);
+
+        // fractional part, consumed for any radix so `0x1.5` is reported
+        // whole, but only valid in decimal; `1._5` is taken whole too, to
+        // be rejected for its misplaced separator
+        if self.peek() == '.' && (is_digit(self.peek_n(1), radix) || self.peek_n(1) == '_') {
             // consume the `.`
             self.advance();
-            while self.peek().is_ascii_digit() {
+            self.digits(radix);
+        }
+
+        if self.options.strict_numbers && (is_xid_start(self.peek()) || self.peek() == '_') {
+            // swallow the rest of the malformed literal
+            while is_xid_continue(self.peek()) {
                 self.advance();
             }
+            let text = self.source[self.start..self.current].to_string();
+            self.add_error(ScannerErrorType::InvalidNumber(text));
+            return;
         }
 
-        let number: f32 = self.source[self.start..self.current].parse().unwrap();
-        self.add_token(TokenType::Number(number));
+        let text = &self.source[self.start..self.current];
+        let digits = if radix == 10 { text } else { &text[2..] };
+        let well_formed = !digits.is_empty()
+            && !digits.starts_with('_')
+            && !digits.ends_with('_')
+            && !digits.contains("_.")
+            && !digits.contains("._")
+            // no hex or binary floats
+            && (radix == 10 || !digits.contains('.'));
+
+        // only copy the digits when there are separators to drop
+        let digits = if digits.contains('_') {
+            Cow::Owned(digits.replace('_', ""))
+        } else {
+            Cow::Borrowed(digits)
+        };
+        let number = match radix {
+            // out of range literals parse as infinity
+            10 => digits.parse().ok().filter(|n: &f64| n.is_finite()),
+            _ => u64::from_str_radix(&digits, radix).ok().map(|n| n as f64),
+        };
+
+        match number {
+            Some(number) if well_formed => self.add_token(TokenType::Number(number)),
+            _ => {
+                let text = text.to_string();
+                self.add_error(ScannerErrorType::InvalidNumber(text));
+            }
+        }
     }
 
-    /// Returns the next character without consuming it.
-    fn peek_next(&self) -> u8 {
-        match self.source.as_bytes().get(self.current + 1) {
-            None => b'\0',
-            Some(&c) => c,
+    /// Consumes a run of digits and `_` separators.
+    fn digits(&mut self, radix: u32) {
+        while is_digit(self.peek(), radix) || self.peek() == '_' {
+            self.advance();
         }
     }
 
     fn identifier(&mut self) {
-        while self.peek().is_ascii_alphanumeric() || self.peek() == b'_' {
+        while is_xid_continue(self.peek()) {
+            self.advance();
+        }
+
+        if self.options.predicate_identifiers
+            && (self.peek() == '?' || (self.peek() == '!' && self.peek_n(1) != '='))
+        {
             self.advance();
         }
 
         let text = &self.source[self.start..self.current];
-        let type_ = match text {
-            "and" => TokenType::And,
-            "class" => TokenType::Class,
-            "else" => TokenType::Else,
-            "false" => TokenType::False,
-            "for" => TokenType::For,
-            "fun" => TokenType::Fun,
-            "if" => TokenType::If,
-            "nil" => TokenType::Nil,
-            "or" => TokenType::Or,
-            "print" => TokenType::Print,
-            "return" => TokenType::Return,
-            "super" => TokenType::Super,
-            "this" => TokenType::This,
-            "true" => TokenType::True,
-            "var" => TokenType::Var,
-            "while" => TokenType::While,
-            _ => TokenType::Identifier(text.to_string()),
-        };
+        let type_ = TokenType::is_keyword(text).unwrap_or(TokenType::Identifier);
 
         self.add_token(type_);
     }
 }
+
+/// Whether `c` is scanned as a digit of a literal in `radix`. Binary
+/// literals take any decimal digit so that `0b12` is reported whole rather
+/// than split in two.
+fn is_digit(c: char, radix: u32) -> bool {
+    match radix {
+        16 => c.is_ascii_hexdigit(),
+        _ => c.is_ascii_digit(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        Scanner::new(source.into()).scan_tokens().unwrap()
+    }
+
+    /// The token types of `source`, without the trailing EOF.
+    fn types(source: &str) -> Vec<TokenType> {
+        let mut types: Vec<_> = tokens(source)
+            .iter()
+            .map(|token| token.token_type().clone())
+            .collect();
+        assert_eq!(types.pop(), Some(TokenType::Eof));
+        types
+    }
+
+    /// The messages of the errors `source` fails with.
+    fn errors(source: &str) -> Vec<String> {
+        Scanner::new(source.into())
+            .scan_tokens()
+            .unwrap_err()
+            .iter()
+            .map(|error| error.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let (result, source) = Scanner::new("變數 = \"café\";".into()).into_tokens_and_source();
+        let tokens = result.unwrap();
+        assert_eq!(tokens[0].token_type(), &TokenType::Identifier);
+        assert_eq!(tokens[0].lexeme(&source), "變數");
+        assert_eq!(tokens[2].token_type(), &TokenType::String("café".into()));
+    }
+
+    #[test]
+    fn identifiers_take_combining_marks() {
+        // `e` followed by a combining acute accent, as in NFD text
+        let (result, source) = Scanner::new("cafe\u{301} x".into()).into_tokens_and_source();
+        let tokens = result.unwrap();
+        assert_eq!(tokens[0].lexeme(&source), "cafe\u{301}");
+        assert_eq!(tokens[1].column(), 7);
+    }
+
+    #[test]
+    fn identifiers_reject_non_xid_characters() {
+        assert_eq!(errors("x²"), ["[line 1:2] Error: unexpected character '²'"]);
+        assert_eq!(
+            errors("\u{301}"),
+            ["[line 1:1] Error: unexpected character '\u{301}'"]
+        );
+    }
+
+    #[test]
+    fn multibyte_characters_advance_by_their_length() {
+        let tokens = tokens("café");
+        assert_eq!(tokens[0].span(), Span::new(0, 5, 1));
+        assert_eq!(tokens[1].span(), Span::new(5, 5, 1));
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let tokens = tokens("\"é\" + x");
+        assert_eq!(tokens[1].column(), 5);
+        assert_eq!(tokens[2].column(), 7);
+        assert_eq!(
+            errors("é + @"),
+            ["[line 1:5] Error: unexpected character '@'"]
+        );
+    }
+
+    #[test]
+    fn multibyte_characters_keep_lines() {
+        let tokens = tokens("\"ü\nü\" ü\nb");
+        assert_eq!((tokens[1].line(), tokens[1].column()), (2, 4));
+        assert_eq!((tokens[2].line(), tokens[2].column()), (3, 1));
+        assert_eq!(types("ü\nb"), vec![TokenType::Identifier; 2]);
+    }
+
+    /// Scans `source` forcing the general, char-decoding path.
+    fn scan_decoding(source: &str) -> Result<Vec<Token>, Vec<ScannerError>> {
+        let mut scanner = Scanner::new(source.into());
+        scanner.ascii = false;
+        scanner.scan_tokens()
+    }
+
+    const ASCII_PROGRAM: &str = "// a comment\n\
+        var x = (1 + 2.5) * 0xFF / 3 ** 2; /* nested /* block */ */\n\
+        \tif (x >= 10 and !false) print \"a\\tb\\x41\\u{42}\";\n\
+        'c' != x_1 .. y % z ? a : b;\n";
+
+    #[test]
+    fn ascii_fast_path_matches_general_path() {
+        assert!(Scanner::new(ASCII_PROGRAM.into()).ascii);
+        assert_eq!(tokens(ASCII_PROGRAM), scan_decoding(ASCII_PROGRAM).unwrap());
+
+        // errors land at the same positions too
+        let source = "1 @\n  \"ab\\q\" #";
+        let fast = errors(source);
+        let general: Vec<_> = scan_decoding(source)
+            .unwrap_err()
+            .iter()
+            .map(|error| error.to_string())
+            .collect();
+        assert_eq!(fast, general);
+    }
+
+    #[test]
+    fn non_ascii_source_takes_general_path() {
+        assert!(!Scanner::new("\"é\"".into()).ascii);
+    }
+
+    /// Compares throughput of the two paths on a large ASCII source. Run
+    /// with `cargo test --release -- --ignored --nocapture ascii_throughput`.
+    #[test]
+    #[ignore]
+    fn ascii_throughput() {
+        extern crate std;
+        use std::println;
+        use std::time::Instant;
+
+        let source = ASCII_PROGRAM.repeat(20_000);
+
+        let start = Instant::now();
+        tokens(&source);
+        let fast = start.elapsed();
+
+        let start = Instant::now();
+        scan_decoding(&source).unwrap();
+        let general = start.elapsed();
+
+        println!(
+            "{} bytes: ascii {:?}, general {:?}",
+            source.len(),
+            fast,
+            general
+        );
+    }
+
+    #[test]
+    fn radix_and_grouped_numbers() {
+        assert_eq!(
+            types("0xFF 0XfF 0b1010 0B11 1_000_000 1.5 12.25_5"),
+            [255.0, 255.0, 10.0, 3.0, 1_000_000.0, 1.5, 12.255].map(TokenType::Number)
+        );
+        // a trailing `.` is still not part of the number
+        assert_eq!(types("10."), [TokenType::Number(10.0), TokenType::Dot]);
+    }
+
+    #[test]
+    fn malformed_numbers() {
+        for text in ["0x_1", "0b_1", "1_", "1_.5", "1._5", "0x1.5", "0x", "0b12"] {
+            assert_eq!(
+                errors(text),
+                [format!("[line 1:1] Error: invalid number '{}'", text)],
+                "{}",
+                text
+            );
+        }
+        assert_eq!(
+            errors("0xFFFFFFFFFFFFFFFFFF"),
+            ["[line 1:1] Error: invalid number '0xFFFFFFFFFFFFFFFFFF'"]
+        );
+    }
+
+    #[test]
+    fn errors_are_reported_as_found() {
+        let mut reported = Vec::new();
+        let result = Scanner::new("@ 1\n2 # 3\n\n  \"x".into())
+            .scan_tokens_reporting(|error| reported.push(error.to_string()));
+        assert!(result.is_none());
+        assert_eq!(
+            reported,
+            [
+                "[line 1:1] Error: unexpected character '@'",
+                "[line 2:3] Error: unexpected character '#'",
+                "[line 4:3] Error: unterminated string",
+            ]
+        );
+
+        let mut reported = 0;
+        let tokens = Scanner::new("1 + 2".into()).scan_tokens_reporting(|_| reported += 1);
+        assert_eq!(tokens.map(|tokens| tokens.len()), Some(4));
+        assert_eq!(reported, 0);
+    }
+
+    #[test]
+    fn token_list_is_reserved_up_front() {
+        let source = "abcdefgh ".repeat(100);
+        let tokens = tokens(&source);
+        assert!(tokens.capacity() >= source.len() / 4);
+
+        // same output as pulling tokens one at a time
+        let mut scanner = Scanner::new(source);
+        let pulled: Vec<_> = scanner.tokens().map(Result::unwrap).collect();
+        assert_eq!(tokens, pulled);
+    }
+
+    #[test]
+    fn source_at_the_size_limit() {
+        // ends in a multibyte character, so the last offsets are mid-char
+        let source = "x = \"ab\"\nyé";
+        let tokens = Scanner::with_max_len(source.into(), source.len())
+            .scan_tokens()
+            .unwrap();
+
+        let [.., last, eof] = &tokens[..] else {
+            panic!("too few tokens");
+        };
+        assert_eq!(last.span(), Span::new(9, source.len(), 2));
+        assert_eq!(eof.span(), Span::new(source.len(), source.len(), 2));
+        assert_eq!(eof.column(), 3);
+    }
+
+    #[test]
+    fn source_over_the_size_limit() {
+        let source = "x = 1";
+        let mut scanner = Scanner::with_max_len(source.into(), source.len() - 1);
+        let results: Vec<_> = scanner.tokens().collect();
+
+        let [Err(error), Ok(eof)] = &results[..] else {
+            panic!("expected an error then EOF, got {:?}", results);
+        };
+        assert_eq!(
+            error.to_string(),
+            "[line 1:1] Error: source is longer than 4 bytes"
+        );
+        assert_eq!(eof.token_type(), &TokenType::Eof);
+        assert_eq!(eof.span(), Span::new(source.len(), source.len(), 1));
+    }
+
+    /// The (type, line) of each token pulled from `scanner`.
+    fn pull(scanner: &mut Scanner) -> Vec<(TokenType, usize)> {
+        scanner
+            .tokens()
+            .map(|result| {
+                let token = result.unwrap();
+                (token.token_type().clone(), token.line())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rescan_restarts_lines() {
+        let mut scanner = Scanner::new("a\nb\n".into()).newline_tokens(true);
+        assert_eq!(
+            pull(&mut scanner),
+            [
+                (TokenType::Identifier, 1),
+                (TokenType::Newline, 1),
+                (TokenType::Identifier, 2),
+                (TokenType::Newline, 2),
+                (TokenType::Eof, 3),
+            ]
+        );
+
+        // options are kept, nothing from the previous source is left over
+        scanner.rescan("1\n".into(), false);
+        assert_eq!(
+            pull(&mut scanner),
+            [
+                (TokenType::Number(1.0), 1),
+                (TokenType::Newline, 1),
+                (TokenType::Eof, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn rescan_continues_lines() {
+        let mut scanner = Scanner::new("a\nb\n".into());
+        pull(&mut scanner);
+
+        scanner.rescan("c @\nd".into(), true);
+        let results: Vec<_> = scanner
+            .tokens()
+            .map(|result| result.map_err(|e| e.to_string()))
+            .collect();
+        assert_eq!(results[0].as_ref().unwrap().line(), 3);
+        assert_eq!(
+            results[1].as_ref().unwrap_err(),
+            "[line 3:3] Error: unexpected character '@'"
+        );
+        assert_eq!(results[2].as_ref().unwrap().line(), 4);
+
+        scanner.rescan("e\n\nf".into(), true);
+        let lines = scanner.tokens_by_line().unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0][0].line(), 4);
+        assert!(lines[1].is_empty());
+        assert_eq!(lines[2][0].line(), 6);
+    }
+
+    #[test]
+    fn rescanned_errors_render_the_new_source() {
+        let mut scanner = Scanner::new("a\nb".into());
+        pull(&mut scanner);
+
+        scanner.rescan("1 @".into(), true);
+        let error = scanner.tokens().find_map(Result::err).unwrap();
+        assert_eq!(
+            error.render("1 @"),
+            "[line 2:3] Error: unexpected character '@'\n  |\n2 | 1 @\n  |   ^"
+        );
+    }
+
+    #[test]
+    fn block_comments() {
+        let tokens = tokens("1 /* a\nb */ + 2");
+        let lines: Vec<_> = tokens
+            .iter()
+            .map(|token| (token.token_type().clone(), token.line()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (TokenType::Number(1.0), 1),
+                (TokenType::Plus, 2),
+                (TokenType::Number(2.0), 2),
+                (TokenType::Eof, 2),
+            ]
+        );
+        assert_eq!(
+            types("/* outer /* inner */ still comment */ 1"),
+            [TokenType::Number(1.0)]
+        );
+        assert_eq!(
+            errors("1\n  /* a /* b */\n"),
+            ["[line 2:3] Error: unterminated block comment"]
+        );
+    }
+
+    #[test]
+    fn string_escapes() {
+        let (result, source) = Scanner::new(r#""a\nb\t\r\\\"\0""#.into()).into_tokens_and_source();
+        let tokens = result.unwrap();
+        assert_eq!(
+            tokens[0].token_type(),
+            &TokenType::String("a\nb\t\r\\\"\0".into())
+        );
+        // the lexeme keeps the source text
+        assert_eq!(tokens[0].lexeme(&source), r#""a\nb\t\r\\\"\0""#);
+    }
+
+    #[test]
+    fn invalid_escapes_continue_scanning() {
+        assert_eq!(
+            errors(r#""a\qb" @"#),
+            [
+                r"[line 1:3] Error: invalid escape sequence '\q'",
+                "[line 1:8] Error: unexpected character '@'",
+            ]
+        );
+        // an escaped quote does not close the string
+        assert_eq!(
+            errors(r#""foo\""#),
+            ["[line 1:1] Error: unterminated string"]
+        );
+    }
+
+    #[test]
+    fn numbers_are_doubles() {
+        // parsed as f32 this would collapse to 9007199000000000
+        assert_eq!(
+            types("9007199254740993"),
+            [TokenType::Number(9007199254740993f64)]
+        );
+        assert_eq!(types("0.1"), [TokenType::Number(0.1)]);
+        let huge = "9".repeat(400);
+        assert_eq!(
+            errors(&huge),
+            [format!("[line 1:1] Error: invalid number '{}'", huge)]
+        );
+    }
+
+    #[test]
+    fn tokens_are_pulled_one_at_a_time() {
+        let mut scanner = Scanner::new("var x = 1;".into());
+        let mut tokens = scanner.tokens();
+        for (type_, column) in [
+            (TokenType::Var, 1),
+            (TokenType::Identifier, 5),
+            (TokenType::Equal, 7),
+            (TokenType::Number(1.0), 9),
+            (TokenType::Semicolon, 10),
+            (TokenType::Eof, 11),
+        ] {
+            let token = tokens.next().unwrap().unwrap();
+            assert_eq!((token.token_type(), token.column()), (&type_, column));
+        }
+        assert!(tokens.next().is_none());
+    }
+
+    #[test]
+    fn char_literals() {
+        assert_eq!(
+            types(r"'a' '\n' '\u{1F600}' 'é'"),
+            [
+                TokenType::Char('a'),
+                TokenType::Char('\n'),
+                TokenType::Char('😀'),
+                TokenType::Char('é'),
+            ]
+        );
+        assert_eq!(errors("''"), ["[line 1:1] Error: empty character literal"]);
+        assert_eq!(
+            errors("'ab'"),
+            ["[line 1:1] Error: character literal contains more than one character"]
+        );
+    }
+
+    #[test]
+    fn hex_escapes() {
+        assert_eq!(types(r#""\x41\xe9""#), [TokenType::String("Aé".into())]);
+        for source in [r#""\xZ1""#, r#""\x1""#, r#""\x1G""#] {
+            assert_eq!(
+                errors(source),
+                [r"[line 1:2] Error: invalid escape sequence '\x'"],
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn single_quote_strings() {
+        let scan = |source: &str| {
+            Scanner::new(source.into())
+                .single_quote_strings(true)
+                .scan_tokens()
+        };
+        let tokens = scan(r"'hello\t' 'a'").unwrap();
+        assert_eq!(tokens[0].token_type(), &TokenType::String("hello\t".into()));
+        assert_eq!(tokens[1].token_type(), &TokenType::String("a".into()));
+
+        // one quote style cannot close the other
+        for source in ["'abc\"", "\"abc'"] {
+            let errors = scan(source).unwrap_err();
+            assert_eq!(
+                errors[0].to_string(),
+                "[line 1:1] Error: unterminated string",
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn modulo_and_ternary_operators() {
+        assert_eq!(
+            types("a % b"),
+            [
+                TokenType::Identifier,
+                TokenType::Percent,
+                TokenType::Identifier
+            ]
+        );
+        assert_eq!(
+            types("a ? b : c"),
+            [
+                TokenType::Identifier,
+                TokenType::Question,
+                TokenType::Identifier,
+                TokenType::Colon,
+                TokenType::Identifier,
+            ]
+        );
+    }
+
+    #[test]
+    fn keywords() {
+        assert_eq!(
+            types("print printer"),
+            [TokenType::Print, TokenType::Identifier]
+        );
+        assert_eq!(TokenType::is_keyword("print"), Some(TokenType::Print));
+        assert_eq!(TokenType::is_keyword("Print"), None);
+    }
+
+    #[test]
+    fn preceded_by_whitespace() {
+        let flags = |source: &str| -> Vec<bool> {
+            tokens(source)
+                .iter()
+                .map(|token| token.preceded_by_whitespace())
+                .collect()
+        };
+        // `-5 - 5`, then EOF
+        assert_eq!(flags("-5 - 5"), [false, false, true, true, false]);
+        assert_eq!(flags("a/* c */b // c\n"), [false, true, true]);
+        assert_eq!(flags(" a"), [true, false]);
+    }
+
+    #[test]
+    fn two_character_operators_take_only_their_own_characters() {
+        let id = TokenType::Identifier;
+        for (source, operator) in [
+            ("a<=b", TokenType::LessEqual),
+            ("a>=b", TokenType::GreaterEqual),
+            ("a==b", TokenType::EqualEqual),
+            ("a!=b", TokenType::BangEqual),
+        ] {
+            assert_eq!(
+                types(source),
+                [id.clone(), operator, id.clone()],
+                "{}",
+                source
+            );
+        }
+        // there are no shift operators
+        assert_eq!(
+            types("a<<b"),
+            [id.clone(), TokenType::Less, TokenType::Less, id.clone()]
+        );
+        assert_eq!(
+            types("a>>=b"),
+            [id.clone(), TokenType::Greater, TokenType::GreaterEqual, id]
+        );
+    }
+
+    #[test]
+    fn unterminated_strings_point_at_the_opening_quote() {
+        assert_eq!(errors("\"abc"), ["[line 1:1] Error: unterminated string"]);
+        assert_eq!(
+            errors("1\n  \"abc\n\n\n"),
+            ["[line 2:3] Error: unterminated string"]
+        );
+    }
+
+    #[test]
+    fn render() {
+        let source = "1\n\tx = \"é\" @\n";
+        let errors = Scanner::new(source.into()).scan_tokens().unwrap_err();
+        // the tab is kept and `é` counts as one column
+        assert_eq!(
+            errors[0].render(source),
+            "[line 2:10] Error: unexpected character '@'\n  |\n2 | \tx = \"é\" @\n  | \t        ^"
+        );
+
+        let source = "\n\n\n\n\n\n\n\n\n@";
+        let errors = Scanner::new(source.into()).scan_tokens().unwrap_err();
+        assert_eq!(
+            errors[0].render(source),
+            "[line 10:1] Error: unexpected character '@'\n   |\n10 | @\n   | ^"
+        );
+    }
+
+    #[test]
+    fn scan_stats() {
+        let source = "// sum\nvar a = b + c; /* done */\n";
+        let (tokens, stats) = Scanner::new(source.into())
+            .scan_tokens_with_stats()
+            .unwrap();
+        assert_eq!(stats.tokens, tokens.len());
+        assert_eq!(stats.tokens, 8);
+        assert_eq!(stats.lines, 2);
+        assert_eq!(stats.comments, 2);
+        assert_eq!(stats.count(&TokenType::Identifier), 3);
+        assert_eq!(stats.count(&TokenType::Number(0.0)), 0);
+    }
+
+    #[test]
+    fn predicate_identifiers() {
+        let scan = |source: &str| -> Vec<(TokenType, String)> {
+            let (result, source) = Scanner::new(source.into())
+                .predicate_identifiers(true)
+                .into_tokens_and_source();
+            result
+                .unwrap()
+                .iter()
+                .map(|token| (token.token_type().clone(), token.lexeme(&source).into()))
+                .collect()
+        };
+        assert_eq!(
+            scan("empty? sort!"),
+            [
+                (TokenType::Identifier, "empty?".into()),
+                (TokenType::Identifier, "sort!".into()),
+                (TokenType::Eof, "".into()),
+            ]
+        );
+        // `!=` is still an operator
+        assert_eq!(
+            scan("a!=b"),
+            [
+                (TokenType::Identifier, "a".into()),
+                (TokenType::BangEqual, "!=".into()),
+                (TokenType::Identifier, "b".into()),
+                (TokenType::Eof, "".into()),
+            ]
+        );
+
+        assert_eq!(
+            types("empty?x"),
+            [
+                TokenType::Identifier,
+                TokenType::Question,
+                TokenType::Identifier
+            ]
+        );
+    }
+
+    #[test]
+    fn strict_numbers() {
+        let errors = Scanner::new("123abc".into())
+            .strict_numbers(true)
+            .scan_tokens()
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].error_type(),
+            ScannerErrorType::InvalidNumber(text) if text == "123abc"
+        ));
+
+        assert_eq!(
+            types("123abc"),
+            [TokenType::Number(123.0), TokenType::Identifier]
+        );
+    }
+
+    #[test]
+    fn lint_indentation() {
+        let warnings = |source: &str| -> Vec<String> {
+            let (result, warnings) = Scanner::new(source.into())
+                .lint_indentation(true)
+                .scan_tokens_with_warnings();
+            assert!(result.is_ok());
+            warnings.iter().map(|warning| warning.to_string()).collect()
+        };
+        // reported at the line's first token
+        assert_eq!(
+            warnings("a\n\t b\n"),
+            ["[line 2:3] Warning: indentation mixes tabs and spaces"]
+        );
+        assert!(warnings("a\n\tb\n\t\tc\n    d\n").is_empty());
+    }
+
+    #[test]
+    fn line_comment_marker() {
+        let scan = |source: &str| -> Vec<TokenType> {
+            Scanner::new(source.into())
+                .line_comment_marker("--")
+                .scan_tokens()
+                .unwrap()
+                .iter()
+                .map(|token| token.token_type().clone())
+                .collect()
+        };
+        assert_eq!(
+            scan("1 -- comment\n2"),
+            [
+                TokenType::Number(1.0),
+                TokenType::Number(2.0),
+                TokenType::Eof
+            ]
+        );
+        assert_eq!(
+            scan("a // b"),
+            [
+                TokenType::Identifier,
+                TokenType::Slash,
+                TokenType::Slash,
+                TokenType::Identifier,
+                TokenType::Eof,
+            ]
+        );
+
+        assert_eq!(
+            types("1 // comment\n2"),
+            [TokenType::Number(1.0), TokenType::Number(2.0)]
+        );
+    }
+
+    #[test]
+    fn form_feed_and_vertical_tab_are_whitespace() {
+        let tokens = tokens("a\x0C+\x0Bb");
+        let lines: Vec<_> = tokens
+            .iter()
+            .map(|token| (token.token_type().clone(), token.line()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (TokenType::Identifier, 1),
+                (TokenType::Plus, 1),
+                (TokenType::Identifier, 1),
+                (TokenType::Eof, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn peek_n() {
+        let mut scanner = Scanner::new("ab".into());
+        assert!(scanner.ascii);
+        assert_eq!(
+            [0, 1, 2, 10].map(|n| scanner.peek_n(n)),
+            ['a', 'b', '\0', '\0']
+        );
+        scanner.advance();
+        assert_eq!([0, 1].map(|n| scanner.peek_n(n)), ['b', '\0']);
+
+        // multibyte characters count as one place each
+        let mut scanner = Scanner::new("éa😀".into());
+        assert!(!scanner.ascii);
+        assert_eq!(
+            [0, 1, 2, 3, 10].map(|n| scanner.peek_n(n)),
+            ['é', 'a', '😀', '\0', '\0']
+        );
+        scanner.advance();
+        assert_eq!([0, 1, 2].map(|n| scanner.peek_n(n)), ['a', '😀', '\0']);
+
+        // the general path agrees on ASCII sources
+        let mut scanner = Scanner::new("ab".into());
+        scanner.ascii = false;
+        assert_eq!(
+            [0, 1, 2, 10].map(|n| scanner.peek_n(n)),
+            ['a', 'b', '\0', '\0']
+        );
+    }
+
+    #[test]
+    fn newline_tokens() {
+        let lines: Vec<_> = Scanner::new("\n\na\n\n\nb\n".into())
+            .newline_tokens(true)
+            .scan_tokens()
+            .unwrap()
+            .iter()
+            .map(|token| (token.token_type().clone(), token.line()))
+            .collect();
+        // leading blank lines are dropped and the others collapse
+        assert_eq!(
+            lines,
+            [
+                (TokenType::Identifier, 3),
+                (TokenType::Newline, 3),
+                (TokenType::Identifier, 6),
+                (TokenType::Newline, 6),
+                (TokenType::Eof, 7),
+            ]
+        );
+
+        assert_eq!(
+            types("a\n\nb\n"),
+            [TokenType::Identifier, TokenType::Identifier]
+        );
+    }
+
+    #[test]
+    fn tokens_by_line() {
+        let lines = Scanner::new("a\n\"x\ny\"\nb".into())
+            .tokens_by_line()
+            .unwrap();
+        let counts: Vec<_> = lines.iter().map(Vec::len).collect();
+        // the string belongs to the line it opens on
+        assert_eq!(counts, [1, 1, 0, 1]);
+        assert_eq!(lines[1][0].token_type(), &TokenType::String("x\ny".into()));
+        assert_eq!(lines[3][0].line(), 4);
+    }
+
+    #[test]
+    fn grouped_radix_literals() {
+        assert_eq!(
+            types("0xFF_FF 0b1010_1010 0x1_2_3"),
+            [65535.0, 170.0, 291.0].map(TokenType::Number)
+        );
+        for text in ["0x_FF", "0xFF_", "0b_1010", "0b1010_"] {
+            assert_eq!(
+                errors(text),
+                [format!("[line 1:1] Error: invalid number '{}'", text)],
+                "{}",
+                text
+            );
+        }
+    }
+}
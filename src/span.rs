@@ -0,0 +1,125 @@
+use alloc::vec::Vec;
+
+/// A region of source code, as byte offsets into the source plus the line
+/// the region starts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset one past the last character.
+    pub end: usize,
+    /// The line number of the first character.
+    pub line: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize) -> Self {
+        Self { start, end, line }
+    }
+
+    /// Returns the smallest span covering both `a` and `b`.
+    pub fn merge(a: Span, b: Span) -> Span {
+        let first = if a.start <= b.start { a } else { b };
+        Span {
+            start: first.start,
+            end: a.end.max(b.end),
+            line: first.line,
+        }
+    }
+}
+
+/// Maps byte offsets back to line and column numbers. The start of each line
+/// is computed once, so finding the line is a binary search; the column is
+/// then counted along that line.
+pub struct SourceMap<'a> {
+    source: &'a str,
+    /// Byte offset of the first character of each line.
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = core::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Returns the 1-based line and column of a byte offset. Columns count
+    /// characters, like the scanner's. The end of the source (`source.len()`)
+    /// maps to the position just after the last character.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        // number of lines starting at or before the offset
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let column = self.source[line_start..offset].chars().count() + 1;
+        (line, column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn line_starts() {
+        let map = SourceMap::new("ab\ncd\n\nef");
+        assert_eq!(map.line_col(0), (1, 1));
+        assert_eq!(map.line_col(3), (2, 1));
+        assert_eq!(map.line_col(6), (3, 1));
+        assert_eq!(map.line_col(7), (4, 1));
+    }
+
+    #[test]
+    fn mid_line() {
+        let map = SourceMap::new("ab\ncd");
+        assert_eq!(map.line_col(1), (1, 2));
+        // the line break belongs to the line it ends
+        assert_eq!(map.line_col(2), (1, 3));
+        assert_eq!(map.line_col(4), (2, 2));
+    }
+
+    #[test]
+    fn end_of_source() {
+        assert_eq!(SourceMap::new("ab\ncd").line_col(5), (2, 3));
+        assert_eq!(SourceMap::new("ab\n").line_col(3), (2, 1));
+        assert_eq!(SourceMap::new("").line_col(0), (1, 1));
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let source = "é + @";
+        let map = SourceMap::new(source);
+        assert_eq!(map.line_col(source.find('@').unwrap()), (1, 5));
+
+        // agrees with the columns the scanner reports
+        let tokens = Scanner::new("é\n  \"ü\" x".into()).scan_tokens().unwrap();
+        let map = SourceMap::new("é\n  \"ü\" x");
+        for token in tokens {
+            assert_eq!(
+                map.line_col(token.span().start),
+                (token.line(), token.column())
+            );
+        }
+    }
+
+    #[test]
+    fn merge() {
+        let (result, source) = Scanner::new("a +\n  bc".into()).into_tokens_and_source();
+        let tokens = result.unwrap();
+        let (a, bc) = (tokens[0].span(), tokens[2].span());
+        assert_eq!(a, Span::new(0, 1, 1));
+        assert_eq!(bc, Span::new(6, 8, 2));
+
+        let merged = Span::merge(a, bc);
+        assert_eq!(merged, Span::new(0, 8, 1));
+        assert_eq!(&source[merged.start..merged.end], "a +\n  bc");
+        // the order of the arguments does not matter
+        assert_eq!(Span::merge(bc, a), merged);
+        assert_eq!(Span::merge(a, a), a);
+    }
+}
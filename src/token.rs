@@ -1,41 +1,236 @@
-#[derive(Debug, Clone)]
+use alloc::string::String;
+use core::fmt;
+
+use crate::span::Span;
+
+#[derive(Debug, Clone, PartialEq)]
 #[rustfmt::skip]
 pub enum TokenType {
     // single-character tokens
     LeftParen, RightParen, LeftBrace, RightBrace,
-    Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
+    Comma, Minus, Plus, Semicolon, Slash, Percent,
+    Question, Colon,
 
     // one or two character tokens
+    Dot, DotDot,
     Bang, BangEqual,
     Equal, EqualEqual,
     Greater, GreaterEqual,
     Less, LessEqual,
+    Star, StarStar,
 
-    // literals
-    Identifier(String),
+    // literals; an identifier's name is its lexeme
+    Identifier,
     String(String),
-    Number(f32),
+    Char(char),
+    Number(f64),
 
     // keywords
     And, Class, Else, False, Fun, For, If, Nil, Or,
     Print, Return, Super, This, True, Var, While,
 
+    // only emitted when the scanner is asked for newline tokens
+    Newline,
+
     Eof,
 }
 
-#[derive(Debug, Clone)]
+impl TokenType {
+    /// The keyword spelled `text`, if it is one.
+    pub fn is_keyword(text: &str) -> Option<TokenType> {
+        let type_ = match text {
+            "and" => TokenType::And,
+            "class" => TokenType::Class,
+            "else" => TokenType::Else,
+            "false" => TokenType::False,
+            "for" => TokenType::For,
+            "fun" => TokenType::Fun,
+            "if" => TokenType::If,
+            "nil" => TokenType::Nil,
+            "or" => TokenType::Or,
+            "print" => TokenType::Print,
+            "return" => TokenType::Return,
+            "super" => TokenType::Super,
+            "this" => TokenType::This,
+            "true" => TokenType::True,
+            "var" => TokenType::Var,
+            "while" => TokenType::While,
+            _ => return None,
+        };
+        Some(type_)
+    }
+
+    /// The name of the token's kind, without any payload, as used in
+    /// `--emit=tokens-json`. Unlike `Debug` output, it is kept stable.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::LeftParen => "LeftParen",
+            Self::RightParen => "RightParen",
+            Self::LeftBrace => "LeftBrace",
+            Self::RightBrace => "RightBrace",
+            Self::Comma => "Comma",
+            Self::Minus => "Minus",
+            Self::Plus => "Plus",
+            Self::Semicolon => "Semicolon",
+            Self::Slash => "Slash",
+            Self::Percent => "Percent",
+            Self::Question => "Question",
+            Self::Colon => "Colon",
+            Self::Dot => "Dot",
+            Self::DotDot => "DotDot",
+            Self::Bang => "Bang",
+            Self::BangEqual => "BangEqual",
+            Self::Equal => "Equal",
+            Self::EqualEqual => "EqualEqual",
+            Self::Greater => "Greater",
+            Self::GreaterEqual => "GreaterEqual",
+            Self::Less => "Less",
+            Self::LessEqual => "LessEqual",
+            Self::Star => "Star",
+            Self::StarStar => "StarStar",
+            Self::Identifier => "Identifier",
+            Self::String(_) => "String",
+            Self::Char(_) => "Char",
+            Self::Number(_) => "Number",
+            Self::And => "And",
+            Self::Class => "Class",
+            Self::Else => "Else",
+            Self::False => "False",
+            Self::Fun => "Fun",
+            Self::For => "For",
+            Self::If => "If",
+            Self::Nil => "Nil",
+            Self::Or => "Or",
+            Self::Print => "Print",
+            Self::Return => "Return",
+            Self::Super => "Super",
+            Self::This => "This",
+            Self::True => "True",
+            Self::Var => "Var",
+            Self::While => "While",
+            Self::Newline => "Newline",
+            Self::Eof => "Eof",
+        }
+    }
+}
+
+/// Prints the token as it is written in source, with literals in canonical
+/// form. Identifiers, whose names are only in the source, print as
+/// `identifier`.
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Self::LeftParen => "(",
+            Self::RightParen => ")",
+            Self::LeftBrace => "{",
+            Self::RightBrace => "}",
+            Self::Comma => ",",
+            Self::Minus => "-",
+            Self::Plus => "+",
+            Self::Semicolon => ";",
+            Self::Slash => "/",
+            Self::Percent => "%",
+            Self::Question => "?",
+            Self::Colon => ":",
+            Self::Dot => ".",
+            Self::DotDot => "..",
+            Self::Bang => "!",
+            Self::BangEqual => "!=",
+            Self::Equal => "=",
+            Self::EqualEqual => "==",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Star => "*",
+            Self::StarStar => "**",
+            Self::Identifier => "identifier",
+            Self::String(string) => return write!(f, "{:?}", string),
+            Self::Char(c) => return write!(f, "{:?}", c),
+            Self::Number(number) => return write!(f, "{}", number),
+            Self::And => "and",
+            Self::Class => "class",
+            Self::Else => "else",
+            Self::False => "false",
+            Self::Fun => "fun",
+            Self::For => "for",
+            Self::If => "if",
+            Self::Nil => "nil",
+            Self::Or => "or",
+            Self::Print => "print",
+            Self::Return => "return",
+            Self::Super => "super",
+            Self::This => "this",
+            Self::True => "true",
+            Self::Var => "var",
+            Self::While => "while",
+            Self::Newline => "\n",
+            Self::Eof => "",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// A token. The lexeme is not stored; it is sliced out of the source on
+/// demand through the token's span.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     type_: TokenType,
-    lexeme: String,
-    line: usize,
+    span: Span,
+    /// The 1-based column of the first character.
+    column: usize,
+    /// Whether whitespace or a comment came between this token and the
+    /// previous one, telling `-5` from `- 5`.
+    preceded_by_whitespace: bool,
 }
 
 impl Token {
-    pub fn new(type_: TokenType, lexeme: String, line: usize) -> Self {
+    pub fn new(type_: TokenType, span: Span, column: usize, preceded_by_whitespace: bool) -> Self {
         Self {
             type_,
-            lexeme,
-            line,
+            span,
+            column,
+            preceded_by_whitespace,
         }
     }
+
+    pub fn token_type(&self) -> &TokenType {
+        &self.type_
+    }
+
+    /// The text of the token in `source`, which must be the source it was
+    /// scanned from.
+    pub fn lexeme<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.span.start..self.span.end]
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.line
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn preceded_by_whitespace(&self) -> bool {
+        self.preceded_by_whitespace
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_leave_out_payloads() {
+        assert_eq!(TokenType::LeftParen.name(), "LeftParen");
+        assert_eq!(TokenType::Identifier.name(), "Identifier");
+        assert_eq!(TokenType::String("a(b)".into()).name(), "String");
+        assert_eq!(TokenType::Number(1.5).name(), "Number");
+        assert_eq!(TokenType::Eof.name(), "Eof");
+    }
 }
@@ -0,0 +1,51 @@
+//! Counts heap allocations made while scanning, through a counting global
+//! allocator. This file holds a single test so that no other test allocates
+//! concurrently.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rlox::scanner::Scanner;
+use rlox::token::TokenType;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[test]
+fn scanning_does_not_allocate_per_token() {
+    // 9 tokens per repetition, just over 100k tokens in all
+    let source = "foo + 12 * bar_baz - (x) ".repeat(11_112);
+    let scanner = Scanner::new(source);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let (result, source) = scanner.into_tokens_and_source();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    let tokens = result.unwrap();
+    assert_eq!(tokens.len(), 100_009);
+    assert_eq!(tokens[0].token_type(), &TokenType::Identifier);
+    assert_eq!(tokens[0].lexeme(&source), "foo");
+    assert_eq!(tokens[4].lexeme(&source), "bar_baz");
+    // a handful for the token list and bookkeeping, not one per token
+    assert!(allocations < 100, "{} allocations", allocations);
+}
@@ -0,0 +1,101 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use std::{env, fs};
+
+/// Runs the binary on a file holding `source`, with `args` before the path.
+fn run_file(name: &str, source: &str, args: &[&str]) -> Output {
+    let path = env::temp_dir().join(format!("rlox-{}-{}.lox", name, std::process::id()));
+    fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    output
+}
+
+/// Runs the REPL with `input` on stdin.
+fn repl(input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn repl_skips_blank_lines() {
+    let output = repl("\n  \t\n1 + 2\n");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "> > > 3\n> ");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+}
+
+#[test]
+fn emits_tokens_as_json() {
+    let output = run_file("json", "x\n  \"é\" + 1", &["--emit=tokens-json"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            "[\n",
+            r#"  {"type": "Identifier", "lexeme": "x", "line": 1, "column": 1, "span": {"start": 0, "end": 1}},"#,
+            "\n",
+            r#"  {"type": "String", "lexeme": "\"é\"", "line": 2, "column": 3, "span": {"start": 4, "end": 8}},"#,
+            "\n",
+            r#"  {"type": "Plus", "lexeme": "+", "line": 2, "column": 7, "span": {"start": 9, "end": 10}},"#,
+            "\n",
+            r#"  {"type": "Number", "lexeme": "1", "line": 2, "column": 9, "span": {"start": 11, "end": 12}},"#,
+            "\n",
+            r#"  {"type": "Eof", "lexeme": "", "line": 2, "column": 10, "span": {"start": 12, "end": 12}}"#,
+            "\n]\n",
+        )
+    );
+}
+
+#[test]
+fn exit_codes() {
+    let output = run_file("error", "@", &["--emit=tokens-json"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(output.stdout, b"");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .arg("--emit=tokens-json")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(64));
+}
+
+#[test]
+fn prints_each_token_with_its_lexeme() {
+    let output = run_file("debug", "var foo", &[]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].contains("Var") && lines[0].ends_with(" \"var\""));
+    assert!(lines[1].contains("Identifier") && lines[1].ends_with(" \"foo\""));
+    assert!(lines[2].contains("Eof") && lines[2].ends_with(" \"\""));
+}
+
+#[test]
+fn repl_continues_unfinished_input() {
+    let output = repl("(1 +\n2)\n");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "> ... 3\n> ");
+}
+
+#[test]
+fn repl_echoes_strings_quoted() {
+    let output = repl("\"a\" + \"b\"\n");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "> \"ab\"\n> ");
+}
@@ -0,0 +1,45 @@
+use rlox::scanner::ScannerErrorType;
+use rlox::token::TokenType;
+
+#[test]
+fn returns_the_tokens() {
+    let (result, warnings) = rlox::run("1 + x");
+    let types: Vec<TokenType> = result
+        .unwrap()
+        .iter()
+        .map(|token| token.token_type().clone())
+        .collect();
+    assert_eq!(
+        types,
+        [
+            TokenType::Number(1.0),
+            TokenType::Plus,
+            TokenType::Identifier,
+            TokenType::Eof
+        ]
+    );
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn returns_every_error() {
+    let (result, _) = rlox::run("@ 1\n\"abc");
+    let errors = result.unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(
+        errors[0].error_type(),
+        ScannerErrorType::UnexpectedCharacter('@')
+    ));
+    assert_eq!(
+        errors[1].to_string(),
+        "[line 2:1] Error: unterminated string"
+    );
+}
+
+#[test]
+fn returns_warnings_with_the_tokens() {
+    let (result, warnings) = rlox::run("1 => 2");
+    assert!(result.is_ok());
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].to_string().starts_with("[line 1:3] Warning:"));
+}